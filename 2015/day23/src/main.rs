@@ -1,3 +1,4 @@
+use regvm::turing::{self, Inst};
 
 fn main() {
     use std::time::Instant;
@@ -11,81 +12,23 @@ fn main() {
     println!("Part 2: {} ({:?})", part_two(&program), t.elapsed());
 }
 
-fn part_one(program: &[Cmd]) -> u32 {
-    let mut ip  = 0;
-    let mut reg: [u32;2] = [0, 0];
-    while ip < program.len() {
-        ip = program[ip].exec(ip, &mut reg);
-    }
-
-    reg[1]
-}
-
-fn part_two(program: &[Cmd]) -> u32 {
-    let mut ip = 0;
-    let mut reg: [u32;2] = [1, 0];
-    while ip < program.len() {
-        ip = program[ip].exec(ip, &mut reg);
-    }
+fn part_one(program: &[Inst]) -> u64 {
+    let mut m = turing::machine(program.to_vec());
+    m.run();
 
-    reg[1]
+    m.reg(1)
 }
 
-fn load(input: &str) -> Vec<Cmd> {
-    use Cmd::*;
+fn part_two(program: &[Inst]) -> u64 {
+    let mut m = turing::machine(program.to_vec());
+    m.set_reg(0, 1);
+    m.run();
 
-    input.lines()
-        .map(|l| {
-            let mut it = l.split(' ');
-            let cmd = it.next().unwrap();
-            let reg = it.next().unwrap();
-            if cmd == "jmp" {
-                let offset = reg.parse::<i32>().unwrap();
-                jmp(offset)
-            } else {
-                let reg = reg.starts_with('b') as usize;
-                match cmd {
-                    "hlf" => hlf(reg),
-                    "tpl" => tpl(reg),
-                    "jie" => {
-                        let offset = it.next().unwrap().parse::<i32>().unwrap();
-                        jie(reg, offset)
-                    },
-                    "jio" => {
-                        let offset = it.next().unwrap().parse::<i32>().unwrap();
-                        jio(reg, offset)
-                    },
-                    _ => inc(reg)
-                }
-            }
-        })
-        .collect()
+    m.reg(1)
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
-enum Cmd {
-    hlf(usize),
-    tpl(usize),
-    inc(usize),
-    jmp(i32),
-    jie(usize, i32),
-    jio(usize, i32),
-}
-
-impl Cmd {
-    fn exec(&self, ip: usize, reg: &mut [u32;2]) -> usize {
-        use Cmd::*;
-
-        match self {
-            hlf(r) => { reg[*r] /= 2; ip+1 },
-            tpl(r) => { reg[*r] *= 3; ip+1 },
-            inc(r) => { reg[*r] += 1; ip+1 },
-            jmp(n) => (ip as i32 + n) as usize,
-            jie(r, n) => if reg[*r] % 2 == 0 { (ip as i32 + n) as usize } else { ip+1 },
-            jio(r, n) => if reg[*r] == 1 { (ip as i32 + n) as usize } else { ip+1 },
-        }
-    }
+fn load(input: &str) -> Vec<Inst> {
+    turing::parse(input).unwrap()
 }
 
 
@@ -10,11 +10,9 @@ fn main() {
     println!("Part 1: {} ({:?})", lcd.iter().sum::<usize>(), t2 - t1);
 
     let t1 = Instant::now();
-    part_two(&lcd);
+    let code = part_two(&lcd);
     let t2 = Instant::now();
-    println!("Part 2: ({:?})", t2 - t1);
-
-    // ZJHRKCPLYJ
+    println!("Part 2: {} ({:?})", code, t2 - t1);
 }
 
 #[derive(Debug)]
@@ -74,11 +72,11 @@ fn part_one(actions: &[Action]) -> [usize;300] {
     })
 }
 
-fn part_two(grid: &[usize]) {
-    (0..6).for_each(|y| {
-        (0..50).for_each(|x| print!("{}", if grid[y*50+x] == 1 { '#' } else { '.' }));
-        println!();
-    });
+fn part_two(grid: &[usize]) -> String {
+    let mut screen = ocr::Bitmap::new(50, 6);
+    (0..6).for_each(|y| (0..50).for_each(|x| screen.set(x, y, grid[y*50+x] == 1)));
+
+    ocr::read(&screen).unwrap_or_else(|e| panic!("{e}"))
 }
 
 
@@ -94,5 +92,6 @@ mod tests {
     
         let lcd = part_one(&actions);
         assert_eq!(lcd.iter().sum::<usize>(), 110);
+        assert_eq!(part_two(&lcd), "ZJHRKCPLYJ");
     }
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+//! With `a` set to 1 the program counts the composite numbers from `b`
+//! to `c` in steps of 17 or so, testing each one by trying every pair
+//! of factors. That would take far too long to run, so check that's
+//! the loop the program has, run the setup to find `b` and `c`, and
+//! count them here.
+
+use regvm::duet::{self, Inst};
+use regvm::Operand;
+
+/// The loop after the setup, up to the step added to `b`.
+const LOOP: &str = "\
+set f 1
+set d 2
+set e 2
+set g d
+mul g e
+sub g b
+jnz g 2
+set f 0
+sub e -1
+set g e
+sub g b
+jnz g -8
+sub d -1
+set g d
+sub g b
+jnz g -13
+jnz f 2
+sub h -1
+set g b
+sub g c
+jnz g 2
+jnz 1 3
+";
+
+/// The composite counting loop with its range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Counter {
+    pub b: i64,
+    pub c: i64,
+    pub step: i64,
+    /// The count before the loop starts.
+    pub h: i64,
+}
+
+impl Counter {
+    pub fn count(&self) -> i64 {
+        let composite = |n: &i64| (2..).take_while(|d| d * d <= *n).any(|d| n % d == 0);
+
+        (self.b..=self.c)
+            .step_by(self.step as usize)
+            .filter(composite)
+            .count() as i64 + self.h
+    }
+}
+
+/// Find the counting loop in a program and what it counts with `a`
+/// set to 1.
+pub fn analyse(program: &[Inst]) -> Result<Counter, String> {
+    let expected = duet::parse(LOOP).unwrap();
+    let start = program.len().checked_sub(expected.len() + 2)
+        .ok_or("The program is too short to hold the counting loop")?;
+
+    let step = match program[program.len() - 2..] {
+        [Inst::Sub(b, Operand::Imm(n)), Inst::Jnz(Operand::Imm(1), Operand::Imm(back))]
+            if b == duet::reg('b') && back == -(expected.len() as i64 + 1) => -n,
+        _ => return Err("The program doesn't end by stepping b and jumping back to the loop".to_string()),
+    };
+
+    if let Some(i) = (0..expected.len()).find(|i| program[start + i] != expected[*i]) {
+        return Err(format!(
+            "Instruction {} is {:?} where the counting loop has {:?}",
+            start + i, program[start + i], expected[i]
+        ))
+    }
+
+    // The setup is straight line code apart from skipping the part
+    // one values, so it's quick to run.
+    let mut m = duet::machine(program.to_vec());
+    m.set_reg(duet::reg('a'), 1);
+    m.run_until(|m| m.ip() == start || m.steps() > 1000);
+    if m.ip() != start {
+        return Err("The setup never reaches the counting loop".to_string())
+    }
+
+    let [b, c, h] = ['b', 'c', 'h'].map(|r| m.reg(duet::reg(r)));
+    if b < 2 || step <= 0 || c < b || (c - b) % step != 0 {
+        return Err(format!("b = {b} never reaches c = {c} in steps of {step}"))
+    }
+
+    Ok(Counter { b, c, step, h })
+}
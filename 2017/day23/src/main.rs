@@ -1,3 +1,8 @@
+use regvm::duet::{self, Inst};
+
+mod coprocessor;
+
+
 fn main() {
     use std::{fs, time::Instant};
     
@@ -10,156 +15,36 @@ fn main() {
     println!("Part 1: {} {:?}", calls, t2 - t1);
 
     let t1 = Instant::now();
-    let count = part_two();
+    let count = part_two(&program);
     let t2 = Instant::now();
     println!("Part 2: {} {:?}", count, t2 - t1);
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Value {
-    Number(i64),
-    Register(char)
-}
-
-type Registers = [i64;8];
-
-impl Value {
-    fn sub(&self, reg: &mut Registers, n: i64) {
-        match self {
-            Value::Number(_)   => panic!("Can only 'sub' register"),
-            Value::Register(c) => {
-                let r = (*c as u8 - b'a') as usize;
-                reg[r] -= n
-            }
-        }
-    }
-
-    fn mul(&self, reg: &mut Registers, n: i64) {
-        match self {
-            Value::Number(_)   => panic!("Can only 'mul' register"),
-            Value::Register(c) => {
-                let r = (*c as u8 - b'a') as usize;
-                reg[r] *= n
-            }
-        }
-    }
-
-    fn set(&self, reg: &mut Registers, n: i64) {
-        match self {
-            Value::Number(_)   => panic!("Can only 'set' register"),
-            Value::Register(c) => {
-                let r = (*c as u8 - b'a') as usize;
-                reg[r] = n
-            }
-        }
-    }
-
-    fn get(&self, reg: &Registers) -> i64 {
-        match self {
-            Value::Number(n)   => *n,
-            Value::Register(c) => {
-                let r = (*c as u8 - b'a') as usize;
-                reg[r]
-            }
-        }
-    }
-}
-
-struct State {
-    ip: usize,
-    reg: Registers,
-    mul: i64,
-}
-
-impl State {
-    fn new() -> State {
-        State { ip: 0, reg: [0i64;8], mul: 0 }
-    }
+fn load(input: &str) -> Vec<Inst> {
+    duet::parse(input).unwrap()
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug)]
-enum Cmd {
-    set(Value, Value),
-    sub(Value, Value),
-    mul(Value, Value),
-    jnz(Value, Value),
-}
-
-impl Cmd {
-    fn exec(&self, st: &mut State) -> usize {
-        use Cmd::*;
-
-        let reg = st.reg;
-        match self {
-            set(a, b) => { a.set(&mut st.reg, b.get(&reg)); st.ip + 1 },
-            sub(a, b) => { a.sub(&mut st.reg, b.get(&reg)); st.ip + 1 },
-            mul(a, b) => {
-                a.mul(&mut st.reg, b.get(&reg));
-                st.mul += 1;
-                st.ip + 1
-            },
-            jnz(a, b) => {
-                if a.get(&st.reg) != 0 { 
-                    st.ip.wrapping_add(b.get(&st.reg) as usize)
-                } else {
-                    st.ip + 1
-                }
-            },
-        }
-    }
-}
+fn part_one(program: &[Inst]) -> i64 {
+    let mut m = duet::machine(program.to_vec());
+    let mut mul = 0;
 
-fn load(input: &str) -> Vec<Cmd> {
-    use Cmd::*;
-
-    input.lines().map(|s| {
-        let mut it = s.split(' ');
-        let cmd = it.next().unwrap();
-        let p1 = get_value(it.next().unwrap());
-        let p2 = get_value(it.next().unwrap());
-
-        match cmd {
-            "set" => set(p1, p2),
-            "sub" => sub(p1, p2),
-            "mul" => mul(p1, p2),
-            "jnz" => jnz(p1, p2),
-            _ => panic!("Unknown command: {cmd}")
+    m.run_until(|m| {
+        if let Inst::Mul(..) = m.program()[m.ip()] {
+            mul += 1;
         }
-    })
-    .collect()
-}
-
-fn part_one(program: &[Cmd]) -> i64 {
-    let mut st = State::new();
-
-    while st.ip < program.len() {
-        st.ip = program[st.ip].exec(&mut st);
-    }
+        false
+    });
 
-    st.mul
+    mul
 }
 
-fn part_two() -> usize {
-    let sqrt = |n: i64| (n as f64).sqrt() as i64;
-    let not_prime = |n: i64| (2..sqrt(n) + 1).any(|v| n % v == 0);
-    
-    // From notes & internet...sigh
-    (106700..123717)
-        .filter(|n| (n - 106700) % 17 == 0)
-        .filter(|n| not_prime(*n))
-        .count()
-}
-
-fn get_value(s: &str) -> Value {
-    if let Ok(n) = s.parse::<i64>() {
-        Value::Number(n)
-    } else {
-        Value::Register(s.chars().next().unwrap())
+fn part_two(program: &[Inst]) -> i64 {
+    match coprocessor::analyse(program) {
+        Ok(counter) => counter.count(),
+        Err(e) => panic!("Can't count composites: {e}"),
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,7 +58,66 @@ mod tests {
         let calls = part_one(&program);
         assert_eq!(calls, 4225);
     
-        let count = part_two();
+        let count = part_two(&program);
         assert_eq!(count, 905);
     }
+
+    const PROGRAM: &str = "\
+set b 67
+set c b
+jnz a 2
+jnz 1 5
+mul b 100
+sub b -100000
+set c b
+sub c -17000
+set f 1
+set d 2
+set e 2
+set g d
+mul g e
+sub g b
+jnz g 2
+set f 0
+sub e -1
+set g e
+sub g b
+jnz g -8
+sub d -1
+set g d
+sub g b
+jnz g -13
+jnz f 2
+sub h -1
+set g b
+sub g c
+jnz g 2
+jnz 1 3
+sub b -17
+jnz 1 -23";
+
+    #[test]
+    fn analysis() {
+        let program = load(PROGRAM);
+        let counter = coprocessor::analyse(&program).unwrap();
+        assert_eq!((counter.b, counter.c, counter.step), (106700, 123700, 17));
+        assert_eq!(part_two(&program), 905);
+
+        // Small enough to run: 12, 29 and 46.
+        let input = PROGRAM.replacen("mul b 100\nsub b -100000\nset c b\nsub c -17000", "mul b 0\nsub b -12\nset c b\nsub c -34", 1);
+        let program = load(&input);
+        let mut m = duet::machine(program.clone());
+        m.set_reg(duet::reg('a'), 1);
+        m.run();
+        assert_eq!(m.reg(duet::reg('h')), 2);
+        assert_eq!(part_two(&program), 2);
+
+        let input = PROGRAM.replacen("sub h -1", "sub h -2", 1);
+        let err = coprocessor::analyse(&load(&input)).unwrap_err();
+        assert!(err.starts_with("Instruction 25 "), "{err}");
+
+        let input = PROGRAM.replacen("sub b -17", "sub b -16", 1);
+        let err = coprocessor::analyse(&load(&input)).unwrap_err();
+        assert_eq!(err, "b = 106700 never reaches c = 123700 in steps of 16");
+    }
 }
@@ -1,40 +1,61 @@
 fn main() {
-    let mut points = load(include_str!("./input.txt"));
-
-    // Y axis sort for printing
-    let mut min_x = points[0];
-    let mut min_y = points[0];
-    points.sort_by(|p1, p2| {
-        min_x = if p1.x() < min_x.x() { *p1 } else { min_x };
-        min_y = if p1.y() < min_y.y() { *p1 } else { min_y };
-
-        let t1 = (p1.pos.1, p1.pos.0);
-        let t2 = (p2.pos.1, p2.pos.0);
-        t1.partial_cmp(&t2).unwrap()
-    });
-
-    let secs = if min_x.x() < min_y.y() {
-        i32::abs(min_x.pos.0 / min_x.vel.0)
-    } else {
-        i32::abs(min_y.pos.1 / min_y.vel.1)
-    } + 26;
-
-    println!("Part 1: LKPHZHHJ");
+    let points = load(include_str!("./input.txt"));
+
+    let secs = converge(&points);
+    let message = ocr::read(&sky(&points, secs)).unwrap_or_else(|e| panic!("{}", e));
+
+    println!("Part 1: {message}");
     println!("Part 2: {secs}");
 }
 
 fn load(input: &str) -> Vec<Point> {
     input.lines().map(|s| {
-        let x = s[10..16].trim().parse::<i32>().unwrap();
-        let y = s[18..24].trim().parse::<i32>().unwrap();
-        let dx = s[36..38].trim().parse::<i32>().unwrap();
-        let dy = s[40..42].trim().parse::<i32>().unwrap();
+        let v = s.split(['<', '>', ','])
+            .map(|t| t.trim())
+            .collect::<Vec<_>>();
+
+        let x = v[1].parse::<i32>().unwrap();
+        let y = v[2].parse::<i32>().unwrap();
+        let dx = v[4].parse::<i32>().unwrap();
+        let dy = v[5].parse::<i32>().unwrap();
 
         Point { pos: (x, y), vel: (dx, dy) }
     })
     .collect()
 }
 
+// The points drift together, line up into the message and then
+// drift apart again, so the message shows when the box around them
+// is smallest. Step until the box starts to grow.
+fn converge(points: &[Point]) -> i32 {
+    let mut secs = 0;
+    let mut smallest = area(points, secs);
+    loop {
+        let next = area(points, secs + 1);
+        if next > smallest { return secs }
+
+        smallest = next;
+        secs += 1;
+    }
+}
+
+fn area(points: &[Point], secs: i32) -> i64 {
+    let (x0, y0, x1, y1) = points.iter()
+        .map(|p| p.pos_at(secs))
+        .fold((i32::MAX, i32::MAX, i32::MIN, i32::MIN), |(x0, y0, x1, y1), (x, y)|
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        );
+
+    (x1 - x0 + 1) as i64 * (y1 - y0 + 1) as i64
+}
+
+fn sky(points: &[Point], secs: i32) -> ocr::Bitmap {
+    ocr::Bitmap::from_points(points.iter().map(|p| {
+        let (x, y) = p.pos_at(secs);
+        (x as i64, y as i64)
+    }))
+}
+
 #[derive(Clone, Copy, Debug)]
 struct Point {
     pos: (i32, i32),
@@ -42,15 +63,6 @@ struct Point {
 }
 
 impl Point {
-    fn x(&self) -> i32 {
-        self.pos.0
-    }
-
-    fn y(&self) -> i32 {
-        self.pos.1
-    }
-
-    #[allow(dead_code)]
     fn pos_at(&self, secs: i32) -> (i32, i32) {
         (
             self.pos.0 + (self.vel.0 * secs),
@@ -58,3 +70,52 @@ impl Point {
         )
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let points = load(include_str!("./input.txt"));
+
+        let secs = converge(&points);
+        assert_eq!(ocr::read(&sky(&points, secs)).unwrap(), "LKPHZHHJ");
+
+        // The time the old hand tuned estimate gave for this input
+        let min_x = points.iter().min_by_key(|p| p.pos.0).unwrap();
+        let min_y = points.iter().min_by_key(|p| p.pos.1).unwrap();
+        let estimate = if min_x.pos.0 < min_y.pos.1 {
+            i32::abs(min_x.pos.0 / min_x.vel.0)
+        } else {
+            i32::abs(min_y.pos.1 / min_y.vel.1)
+        } + 26;
+        assert_eq!(secs, estimate);
+    }
+
+    #[test]
+    fn example() {
+        let points = load(include_str!("./example.txt"));
+
+        let secs = converge(&points);
+        assert_eq!(secs, 3);
+
+        let frame = sky(&points, secs);
+        assert_eq!(frame.to_string(), "\
+#...#..###
+#...#...#.
+#...#...#.
+#####...#.
+#...#...#.
+#...#...#.
+#...#...#.
+#...#..###
+");
+
+        // The example's letters are eight rows high, which no font is.
+        let err = ocr::read(&frame).unwrap_err();
+        assert_eq!(err.text, "?");
+        assert_eq!(err.glyphs, [frame]);
+    }
+}
@@ -1,3 +1,6 @@
+use regvm::elfcode::{Inst, Opcode};
+use regvm::Machine;
+
 fn main() {
     use std::time::Instant;
 
@@ -16,13 +19,21 @@ fn main() {
 
 #[derive(Clone, Copy, Debug)]
 struct Sample {
-    inst: [i32;4],
-    reg_a: [i32;4],
-    reg_b: [i32;4],
+    inst: [i64;4],
+    reg_a: [i64;4],
+    reg_b: [i64;4],
+}
+
+impl Sample {
+    fn behaves_like(&self, op: Opcode) -> bool {
+        let mut r = self.reg_a;
+        decode(op, &self.inst).apply(&mut r);
+        r == self.reg_b
+    }
 }
 
 type Samples = Vec<Sample>;
-type Program = Vec<[i32;4]>;
+type Program = Vec<[i64;4]>;
 
 fn load(input: &str) -> (Samples, Program) {
     let sections = input.split("\n\n\n\n").collect::<Vec<_>>();
@@ -36,7 +47,7 @@ fn load(input: &str) -> (Samples, Program) {
 
     let program = sections[1].lines()
         .map(|s| {
-            let mut inst = [0i32;4];
+            let mut inst = [0i64;4];
             let it = s.split(' ');
             fill(it, &mut inst);
             inst
@@ -47,36 +58,19 @@ fn load(input: &str) -> (Samples, Program) {
 }
 
 fn part_one(samples: &Samples) -> usize {
-    use Opcode::*;
-
-    let opcodes = [
-        setr, seti,
-        addr, addi,
-        mulr, muli,
-        banr, bani,
-        borr, bori,
-        gtir, gtri, gtrr,
-        eqir, eqri, eqrr
-    ];
-
     samples.iter()
         .filter(|s|
-            opcodes.iter()
-                .filter(|opc| opc.exec(&s.inst, &s.reg_a) == s.reg_b)
+            Opcode::ALL.iter()
+                .filter(|opc| s.behaves_like(**opc))
                 .count() >= 3
         )
         .count()
 }
 
-fn part_two(samples: &Samples, program: &Program) -> i32 {
-    use Opcode::*;
-
-    let mut opcodes = [
-        setr, seti, addr, addi, mulr, muli, banr, bani, borr, bori,
-        gtir, gtri, gtrr, eqir, eqri, eqrr
-    ].to_vec();
+fn part_two(samples: &Samples, program: &Program) -> i64 {
+    let mut opcodes = Opcode::ALL.to_vec();
 
-    let mut cmds = [setr;16];
+    let mut cmds = [Opcode::setr;16];
     let mut samples = samples.clone();
 
     // Find the samples where only one opcode works and widdle
@@ -90,7 +84,7 @@ fn part_two(samples: &Samples, program: &Program) -> i32 {
             .for_each(|(i, s)| {
                 let v = opcodes.iter()
                     .filter_map(|opc| 
-                        if opc.exec(&s.inst, &s.reg_a) == s.reg_b {
+                        if s.behaves_like(*opc) {
                             Some(*opc)
                         } else {
                             None
@@ -117,18 +111,19 @@ fn part_two(samples: &Samples, program: &Program) -> i32 {
         }
     }
 
-    let mut reg = [0i32;4];
-    for inst in program {
-        reg = cmds[inst[0] as usize].exec(inst, &reg);
-    }
+    let program = program.iter()
+        .map(|v| decode(cmds[v[0] as usize], v))
+        .collect();
 
-    reg[0]
+    let mut m = Machine::new(program, 4);
+    m.run();
+    m.reg(0)
 }
 
 fn make_sample(v: &[&str]) -> Sample {
-    let mut inst  = [0i32;4];
-    let mut reg_a = [0i32;4];
-    let mut reg_b = [0i32;4];
+    let mut inst  = [0i64;4];
+    let mut reg_a = [0i64;4];
+    let mut reg_b = [0i64;4];
 
     let it = v[0][9..19].split(", ");
     fill(it, &mut reg_a);
@@ -140,52 +135,12 @@ fn make_sample(v: &[&str]) -> Sample {
     Sample { inst, reg_a, reg_b }
 }
 
-fn fill<'a>(it: impl Iterator<Item=&'a str>, buf: &mut [i32;4]) {
-    it.enumerate().for_each(|(i, s)| buf[i] = s.parse().unwrap());
-}
-
-#[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum Opcode {
-    setr, seti,
-    addr, addi,
-    mulr, muli,
-    banr, bani,
-    borr, bori,
-    gtir, gtri, gtrr,
-    eqir, eqri, eqrr,
+fn decode(op: Opcode, v: &[i64;4]) -> Inst {
+    Inst { op, a: v[1], b: v[2], c: v[3] }
 }
 
-impl Opcode {
-    fn exec(&self, v: &[i32;4], reg: &[i32;4]) -> [i32;4] {
-        use Opcode::*;
-
-        let a = v[1] as usize;
-        let b = v[2] as usize;
-        let c = v[3] as usize;
-
-        let mut r = *reg;
-        match self {
-            setr => r[c] = r[a],
-            seti => r[c] = a as i32,
-            addr => r[c] = r[a] + r[b],
-            addi => r[c] = r[a] + b as i32,
-            mulr => r[c] = r[a] * r[b],
-            muli => r[c] = r[a] * b as i32,
-            banr => r[c] = r[a] & r[b],
-            bani => r[c] = r[a] & b as i32,
-            borr => r[c] = r[a] | r[b],
-            bori => r[c] = r[a] | b as i32,
-            gtir => r[c] = (a as i32 > r[b]) as i32,
-            gtri => r[c] = (r[a] > b as i32) as i32,
-            gtrr => r[c] = (r[a] > r[b]) as i32,
-            eqir => r[c] = (a as i32 == r[b]) as i32,
-            eqri => r[c] = (r[a] == b as i32) as i32,
-            eqrr => r[c] = (r[a] == r[b]) as i32,
-        }
-
-        r
-    }
+fn fill<'a>(it: impl Iterator<Item=&'a str>, buf: &mut [i64;4]) {
+    it.enumerate().for_each(|(i, s)| buf[i] = s.parse().unwrap());
 }
 
 
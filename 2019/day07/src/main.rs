@@ -1,6 +1,7 @@
 use anyhow::Result;
 use itertools::Itertools;
 use std::cell::RefCell;
+use vm::net::{Network, Schedule};
 use vm::Vm;
 
 fn main() {
@@ -46,10 +47,6 @@ impl Amp {
     Ok(())
   }
 
-  pub fn is_running(&self) -> bool {
-    self.vm.borrow().is_running()
-  }
-
   pub fn write(&self, v: i64) {
     let vm = self.vm.borrow_mut();
     let (mut pipe, _) = vm.pipes();
@@ -92,20 +89,22 @@ fn run(program: &str, phases: &[i64]) -> Result<i64> {
 }
 
 fn feedback(program: &str, phases: &[i64]) -> Result<i64> {
-  let mut amps = load(program, phases)?;
+  let mut network = Network::new();
+  for _ in phases {
+    network.add(Vm::new(program)?);
+  }
 
   // Setup the feedback loop
-  amps[4].connect(&amps[0]);
-  amps.iter_mut().try_for_each(|amp| amp.boot())?;
+  let n = network.len();
+  (0..n).for_each(|i| network.connect(i, (i + 1) % n));
+  phases.iter().enumerate().for_each(|(i, &p)| network.vm_mut(i).write(p));
 
   // Set initial signal value
-  amps[0].write(0);
-  while amps[4].is_running() {
-    amps.iter_mut().try_for_each(|amp| amp.cont())?;
-  }
-  let signal = amps.last().unwrap().signal();
+  network.vm_mut(0).write(0);
+  network.run(Schedule::Cooperative)?;
+  let (_, mut pipe) = network.vm(n - 1).pipes();
   
-  Ok(signal)
+  Ok(pipe.flush())
 }
 
 
@@ -3,9 +3,7 @@
 // 0's, 1's and 2's.
 // Creating the image is just stacking the layers and taking the
 // value of the first non-transparent pixel in a given location.
-// Then "draw" the image by writing out the vector in rows and
-// converting values to '*' and ' ' to try and make the letters
-// visible.
+// Then read the letters off the white pixels.
 
 use std::cmp::{Ordering, PartialOrd};
 
@@ -20,11 +18,14 @@ fn main() {
   let min_zeros = layers.iter().min().unwrap();
   println!("{:?} {}", min_zeros, min_zeros.digits());
 
+  println!("{}", message(&layers));
+}
+
+fn message(layers: &[Layer]) -> String {
   let image = layers[0].clone();
   let image = layers.iter().skip(1).fold(image, |i, l| i.stack(l));
-  image.draw();
 
-  // GKCKH
+  ocr::read(&image.bitmap()).unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[allow(dead_code)]
@@ -57,10 +58,12 @@ impl Layer {
     self.ones * self.twos
   }
 
-  pub fn draw(&self) {
-    self.pixels.chunks(25)
-      .map(|l| l.iter().map(|v| if *v == 0u8 { '*' } else { ' ' }).collect())
-      .for_each(|l:String| println!("{l:?}"));
+  pub fn bitmap(&self) -> ocr::Bitmap {
+    let mut bitmap = ocr::Bitmap::new(25, 6);
+    for (i, v) in self.pixels.iter().enumerate() {
+      bitmap.set(i % 25, i / 25, *v == 1);
+    }
+    bitmap
   }
 
   pub fn stack(&self, other: &Self) -> Self {
@@ -94,4 +97,22 @@ impl PartialOrd for Layer {
   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
     self.zeros.partial_cmp(&other.zeros)
   }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_works() {
+    let layers = include_str!("./image.txt")
+      .as_bytes()
+      .chunks(25 * 6)
+      .enumerate()
+      .map(|(i, pixels)| Layer::new(i, pixels))
+      .collect::<Vec<Layer>>();
+
+    assert_eq!(message(&layers), "GKCKH");
+  }
+}
@@ -9,20 +9,7 @@ fn main() {
     robot.paint(Color::White).unwrap();
     println!("Panels painted: {}", robot.painted().len());
 
-    let (height, width) = robot.dimensions();
-    let painted = robot.painted();
-    for y in 0..=height as i32 {
-        for x in 0..width as i32 {
-            let panel = Panel { x, y: -y };
-            match painted.get(&panel).unwrap_or(&Color::Black) {
-                Color::Black => print!(" "),
-                Color::White => print!("#"),
-            }
-        }
-        println!()
-    }
-
-    // CBLPJZCU
+    println!("Registration: {}", robot.registration());
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -81,6 +68,15 @@ impl Robot {
         &self.painted
     }
 
+    /// Read the letters painted white, with y increasing upwards.
+    fn registration(&self) -> String {
+        let white = self.painted.iter()
+            .filter(|(_, color)| matches!(color, Color::White))
+            .map(|(panel, _)| (panel.x as i64, -panel.y as i64));
+
+        ocr::read(&ocr::Bitmap::from_points(white)).unwrap_or_else(|e| panic!("{}", e))
+    }
+
     fn paint(&mut self, start: Color) -> Result<(), &str> {
         let mut loc = Panel { x: 0, y: 0 };
         let (mut stdin, mut stdout) = self.vm.pipes();
@@ -119,22 +115,6 @@ impl Robot {
         Ok(())
     }
 
-    pub fn dimensions(&self) -> (i8, i8) {
-        let mut min_x = 0i8;
-        let mut min_y = 0i8;
-        let mut max_x = 0i8;
-        let mut max_y = 0i8;
-
-        for panel in self.painted.keys() {
-            min_x = i8::min(min_x, panel.x as i8);
-            min_y = i8::min(min_y, panel.y as i8);
-            max_x = i8::max(max_x, panel.x as i8);
-            max_y = i8::max(max_y, panel.y as i8);
-        }
-
-        (max_y - min_y, max_x - min_x)
-    }
-
     fn turn_left(&self) -> Direction {
         match self.facing {
             Direction::Up    => Direction::Left,
@@ -167,4 +147,13 @@ mod tests {
 
         assert_eq!(robot.painted().len(), 2276);
     }
+
+    #[test]
+    fn registration() {
+        let program = include_str!("./program.txt");
+        let mut robot = Robot::from(program).unwrap();
+        robot.paint(Color::White).unwrap();
+
+        assert_eq!(robot.registration(), "CBLPJZCU");
+    }
 }
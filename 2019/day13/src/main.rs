@@ -1,5 +1,4 @@
-use std::collections::HashSet;
-use vm::Vm;
+use vm::{screen::Screen, Vm};
 
 fn main() {
     let program = include_str!("./program.txt");
@@ -15,54 +14,45 @@ fn part_one(program: &str) -> usize {
     let mut vm = Vm::new(program).unwrap();
     let (_, mut stdout) = vm.pipes();
     vm.exec().unwrap();
-    stdout.drain().chunks(3).filter(|c| c[2] == 2).count()
+
+    let mut screen = Screen::new();
+    screen.draw(&stdout.drain());
+    screen.count(Tile::Block as i64)
 }
 
 fn part_two(program: &str) -> i64 {
     let mut vm = Vm::new(program).unwrap();
     let (mut stdin, mut stdout) = vm.pipes();
+    vm.set_addr(0, 2).unwrap();
 
-    vm.set_addr(0, 2);
+    // Keep the paddle under the ball until every block is broken
+    let mut screen = Screen::new();
     vm.exec().unwrap();
-    let tiles = stdout.drain();
-    let mut blocks: HashSet<(i64, i64)> = tiles.chunks(3)
-        .filter(|c| c[2] == 2)
-        .map(|c| (c[0], c[1]))
-        .collect();
-
-    let mut ball   = find_tile(&tiles, 4).unwrap();
-    let mut paddle = find_tile(&tiles, 3).unwrap();
-    let offset = ball.0 - paddle.0;
-
-    let joystick = offset.cmp(&0) as i32;
-    stdin.write(joystick);
-
-    let mut score = 0;
-    while !blocks.is_empty() {
-        vm.cont().unwrap();
-        for tile in stdout.drain().chunks(3) {
-            match tile {
-                [-1, 0, n] => score = *n,
-                [x,  y, 0] => { blocks.remove(&(*x, *y)); },
-                [x,  y, 3] => paddle = (*x, *y),
-                [x,  y, 4] => ball = (*x, *y),
-                _ => panic!()
-            }
-        };
-
-        let offset = ball.0 - paddle.0;
-        let joystick = offset.cmp(&0) as i32;
+    loop {
+        screen.draw(&stdout.drain());
+        if vm.is_done() || screen.count(Tile::Block as i64) == 0 {
+            break
+        }
+
+        let ball = find_tile(&screen, Tile::Ball).unwrap();
+        let paddle = find_tile(&screen, Tile::Paddle).unwrap();
+        let joystick = (ball.0 - paddle.0).signum();
         stdin.write(joystick);
         vm.cont().unwrap();
     };
 
-    score
+    screen.score().unwrap_or(0)
+}
+
+#[derive(Clone, Copy)]
+enum Tile {
+    Block = 2,
+    Paddle = 3,
+    Ball = 4,
 }
 
-fn find_tile(screen: &[i64], id: i64) -> Option<(i64, i64)> {
-    let tiles = screen.chunks(3).collect::<Vec<&[i64]>>();
-    let idx = tiles.iter().position(|t| t[2] == id)?;
-    Some((tiles[idx][0], tiles[idx][1]))
+fn find_tile(screen: &Screen, tile: Tile) -> Option<(i64, i64)> {
+    screen.find(tile as i64).next()
 }
 
 #[cfg(test)]
@@ -1,102 +1,69 @@
 use anyhow::Result;
-use std::collections::HashMap;
-use std::io;
-use vm::Vm;
+use std::collections::{HashMap, VecDeque};
+use vm::{repl::Repl, Vm};
 
 fn main() {
     let program = include_str!("./program.txt");
-    part_one(program).unwrap();
+    if std::env::args().any(|arg| arg == "--play") {
+        play(program).unwrap();
+        return
+    }
+
+    let board = explore(program).unwrap();
+    let moves = board.shortest_path().unwrap();
+    println!("Moves to oxygen: {moves}");
 
-    let board = include_str!("./board.txt");
-    let steps = part_two(board).unwrap();
+    let steps = board.fill_time().unwrap();
     println!("Oxygen staturation: {steps} minutes");
 }
 
-fn part_one(program: &str) -> Result<(), &str> {
+/// Map the whole section by walking the droid depth first, backing
+/// up once every neighbour of a tile is known.
+fn explore(program: &str) -> Result<Board, &str> {
     let mut droid = Droid::from(program).or(Err("load failed"))?;
     droid.start().or(Err("start failed"))?;
 
     let mut board = Board::new();
-    board.draw(&droid);
-
-    let mut moves = 0;
-    loop {
-        moves += 1;
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).or(Err("readline failed"))?;
-        let cmd = match input.trim() {
-            "q" => break,
-            "w" => Some(Command::North),
-            "a" => Some(Command::West),
-            "s" => Some(Command::South),
-            "d" => Some(Command::East),
-            cmd => {
-                println!("Unknown command: {cmd}");
-                None
-            }
-        };
-        if let Some(cmd) = cmd {
-            let result = droid.step(cmd)?;
-            match result {
-                Status::Wall   => board.update(&droid, Tile::Wall, Some(cmd)),
-                Status::Moved  => board.update(&droid, Tile::Open, None),
-                Status::Oxygen => board.update(&droid, Tile::Oxygen, None),
-            }
-        }
-
-        board.draw(&droid);
-        println!("Moves: {moves}");
-    };
+    search(&mut droid, &mut board)?;
 
-    Ok(())
+    Ok(board)
 }
 
-type TileMap = HashMap<(usize, usize), Tile>;
-type TilePos = ((usize, usize), Tile);
-
-fn part_two(board: &str) -> Result<i32, &str> {
-    let mut tiles: TileMap = board.lines()
-        .enumerate()
-        .flat_map(move |(y, s)| s.chars().enumerate()
-            .map(move |(x, c)| to_tile((x, y), c))
-        )
-        .collect::<Result<Vec<TilePos>, &str>>()?
-        .into_iter()
-        .collect();
-
-    let oxygen = tiles.iter()
-        .find_map(|(k, &v)|(v == Tile::Oxygen).then_some(k))
-        .ok_or("no oxygen")?;
-
-    let mut steps  = 0;
-    let mut spread = open_tiles(oxygen, &tiles);
-    while !spread.is_empty() {
-        steps += 1;
-        spread.iter().for_each(|&pos| { tiles.insert(pos, Tile::Oxygen); });
-        spread = spread.iter().flat_map(|p| open_tiles(p, &tiles)).collect();
-    };
-
-    Ok(steps)
-}
+fn search(droid: &mut Droid, board: &mut Board) -> Result<(), &'static str> {
+    use Command::*;
 
-fn to_tile(pos: (usize, usize), c: char) -> Result<TilePos, &'static str> {
-    match c {
-        '#' => Ok((pos, Tile::Wall)),
-        '.' => Ok((pos, Tile::Open)),
-        'O' => Ok((pos, Tile::Oxygen)),
-        _  => Err("invalid tile")
+    for cmd in [North, South, West, East] {
+        if board.tiles.contains_key(&step(droid.pos, Some(cmd))) {
+            continue
+        }
+
+        let pos = droid.pos;
+        match droid.step(cmd)? {
+            Status::Wall   => board.update(droid, Tile::Wall, Some(cmd)),
+            Status::Moved  => board.update(droid, Tile::Open, None),
+            Status::Oxygen => board.update(droid, Tile::Oxygen, None),
+        }
+        if droid.pos != pos {
+            search(droid, board)?;
+            droid.step(cmd.reverse())?;
+        }
     }
+
+    Ok(())
 }
 
-fn open_tiles(pos: &(usize, usize), tiles: &TileMap) -> Vec<(usize, usize)> {
-    [
-        (pos.0, pos.1 - 1),   // north
-        (pos.0, pos.1 + 1),   // south
-        (pos.0 + 1, pos.1),   // east
-        (pos.0 - 1, pos.1),   // west
-    ].iter()
-    .filter_map(|&p| tiles.get(&p).filter(|&t| *t == Tile::Open).map(|_| p))
-    .collect()
+/// Drive the droid by hand with WASD; it answers 0 for a wall, 1
+/// for a move and 2 for the oxygen system.
+fn play(program: &str) -> Result<()> {
+    use std::io;
+
+    Repl::new(Vm::new(program)?)
+        .alias("w", &(Command::North as i64).to_string())
+        .alias("s", &(Command::South as i64).to_string())
+        .alias("a", &(Command::West as i64).to_string())
+        .alias("d", &(Command::East as i64).to_string())
+        .alias("q", ".q")
+        .run(io::stdin().lock(), io::stdout())
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -115,6 +82,17 @@ enum Command {
     East = 4,
 }
 
+impl Command {
+    fn reverse(self) -> Self {
+        match self {
+            Command::North => Command::South,
+            Command::South => Command::North,
+            Command::East  => Command::West,
+            Command::West  => Command::East,
+        }
+    }
+}
+
 #[derive(PartialEq)]
 enum Status {
     Wall = 0,
@@ -134,8 +112,6 @@ fn step(pos: (i32, i32), cmd: Option<Command>) -> (i32, i32) {
 
 struct Board {
     tiles: HashMap<(i32, i32), Tile>,
-    tl: (i32, i32),
-    br: (i32, i32),
 }
 
 impl Board {
@@ -143,51 +119,52 @@ impl Board {
         let mut tiles = HashMap::new();
         tiles.insert((0, 0), Tile::Start);
 
-        Board { tiles, tl: (0, 0), br: (0, 0) }
+        Board { tiles }
     }
 
-    fn draw(&self, droid: &Droid) {
-        let y0 = self.tl.1;
-        let y1 = self.br.1;
-        let x0 = self.tl.0;
-        let x1 = self.br.0;
-
-        for y in y0..=y1 {
-            let row: String = (x0..=x1).map(|x| match self.tiles.get(&(x, y)) {
-                Some(Tile::Open)   if (x, y) == droid.pos => 'D',
-                Some(Tile::Start)  if (x, y) == droid.pos => 'D',
-                Some(Tile::Oxygen) if (x, y) == droid.pos => 'D',
-                Some(Tile::Open)   => '.',
-                Some(Tile::Wall)   => '#',
-                Some(Tile::Start)  => 'S',
-                Some(Tile::Oxygen) => 'O',
-                None => '?',
-            }).collect();
-            println!("{row}");
-        };
+    fn oxygen(&self) -> Option<(i32, i32)> {
+        self.tiles.iter()
+            .find_map(|(&k, &v)| (v == Tile::Oxygen).then_some(k))
+    }
+
+    fn shortest_path(&self) -> Option<i32> {
+        let oxygen = self.oxygen()?;
+        self.distances((0, 0)).get(&oxygen).copied()
+    }
+
+    /// Minutes for oxygen to spread to every open tile.
+    fn fill_time(&self) -> Option<i32> {
+        let oxygen = self.oxygen()?;
+        self.distances(oxygen).into_values().max()
+    }
+
+    /// Breadth first distance from `from` to every reachable tile.
+    fn distances(&self, from: (i32, i32)) -> HashMap<(i32, i32), i32> {
+        use Command::*;
+
+        let mut dist = HashMap::from([(from, 0)]);
+        let mut q = VecDeque::from([from]);
+        while let Some(pos) = q.pop_front() {
+            let d = dist[&pos];
+            for cmd in [North, South, West, East] {
+                let p = step(pos, Some(cmd));
+                let open = self.tiles.get(&p).is_some_and(|&t| t != Tile::Wall);
+                if open && !dist.contains_key(&p) {
+                    dist.insert(p, d + 1);
+                    q.push_back(p);
+                }
+            }
+        }
+
+        dist
     }
 
     fn update(&mut self, droid: &Droid, tile: Tile, cmd: Option<Command>) {
-        let pos = match tile {
-            Tile::Wall => {
-                let pos = step(droid.pos, cmd);
-                self.tiles.insert(pos, tile);
-                pos
-            },
-            Tile::Open => {
-                self.tiles.insert(droid.pos, tile);
-                droid.pos
-            },
-            Tile::Oxygen => {
-                self.tiles.insert(droid.pos, tile);
-                droid.pos
-            },
-            Tile::Start => droid.pos,
-        };
-        self.tl.0 = self.tl.0.min(pos.0);
-        self.tl.1 = self.tl.1.min(pos.1);
-        self.br.0 = self.br.0.max(pos.0);
-        self.br.1 = self.br.1.max(pos.1);
+        match tile {
+            Tile::Wall => { self.tiles.insert(step(droid.pos, cmd), tile); },
+            Tile::Open | Tile::Oxygen => { self.tiles.insert(droid.pos, tile); },
+            Tile::Start => {},
+        }
     }
 }
 
@@ -230,9 +207,30 @@ mod tests {
 
     #[test]
     fn it_works() {
-        let board = include_str!("./board.txt");
-        let steps = part_two(board).unwrap();
+        let program = include_str!("./program.txt");
+        let board = explore(program).unwrap();
 
-        assert_eq!(steps, 334)
+        assert_eq!(board.shortest_path(), Some(220));
+        assert_eq!(board.fill_time(), Some(334))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn oxygen() {
+        let map = [" ##   ", "#..## ", "#.#..#", "#.O.# ", " ###  "];
+        let tiles = map.iter()
+            .enumerate()
+            .flat_map(|(y, s)| s.chars().enumerate()
+                .filter_map(move |(x, c)| match c {
+                    '#' => Some(Tile::Wall),
+                    '.' => Some(Tile::Open),
+                    'O' => Some(Tile::Oxygen),
+                    _ => None,
+                }.map(|t| ((x as i32 - 2, y as i32 - 1), t)))
+            )
+            .collect();
+        let board = Board { tiles };
+
+        assert_eq!(board.shortest_path(), Some(4));
+        assert_eq!(board.fill_time(), Some(4));
+    }
+}
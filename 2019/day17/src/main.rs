@@ -1,7 +1,6 @@
-use anyhow::Result;
-use std::cmp::max;
+use anyhow::{anyhow, Result};
 use std::collections::HashSet;
-use vm::Vm;
+use vm::{ascii::Ascii, screen::Screen, Vm};
 
 type Skaffold = HashSet<(i32, i32)>;
 
@@ -15,91 +14,197 @@ fn main() {
 }
 
 fn part_one(program: &str) -> Result<i32> {
-    let mut vm = Vm::new(program)?;
-    let (_, mut stdout) = vm.pipes();
-    vm.exec()?;
-
-    let mut x = 0;
-    let mut y = 0;
-    let mut cols = 0;
-    let mut robot = (0, 0);
-    let mut facing = Direction::Up;
-    let mut skaffold = HashSet::new();
-
-    stdout.drain().iter()
-        .for_each(|v| {
-            match v {
-                10  => { cols = max(x, cols); y += 1; x = -1; },
-                35  => { skaffold.insert((x, y)); },
-                60  => { robot = (x, y); facing = Direction::Left; },
-                62  => { robot = (x, y); facing = Direction::Right; },
-                94  => { robot = (x, y); facing = Direction::Up; },
-                118 => { robot = (x, y); facing = Direction::Down; },
-                _   => ()
-            };
-            x += 1;
-        });
-
-    let calibration: i32 = skaffold.iter()
-        .filter(|p| is_intersection(p, &skaffold))
+    let view = camera(program)?;
+    let calibration: i32 = view.skaffold.iter()
+        .filter(|p| is_intersection(p, &view.skaffold))
         .map(|p| p.0 * p.1)
         .sum();
 
     Ok(calibration)
 }
 
-fn part_two(program: &str) -> Result<i64, &str> {
-    // M: A,C,C,B,B,A,C,C
-    // A: L,12,R,4,R,4
-    // B: R,12,R,4,L,6,L,8,L,8
-    // C: R,12,R,4,L,12
-    let rules = b"\
-        A,C,C,B,B,A,A,C,C,B\n\
-        L,12,R,4,R,4\n\
-        R,12,R,4,L,6,L,8,L,8\n\
-        R,12,R,4,L,12\n";
-
-    let mut vm = Vm::new(program).or(Err("Load failed"))?;
+fn part_two(program: &str) -> Result<i64> {
+    let view = camera(program)?;
+    let path = walk(&view);
+    if path.is_empty() {
+        return Err(anyhow!("No scaffold next to the robot at {:?}", view.robot))
+    }
+    let rules = compress(&path)
+        .ok_or_else(|| anyhow!("No movement rules for {}", path.join(",")))?;
 
-    vm.edit_program(0, 2);
-    rules.iter().for_each(|&v| vm.write(v as i64));
-    vm.write(b'n'); vm.write(b'\n'); // feed
-    vm.exec().or(Err("Exec failed"))?;
+    let mut vm = Vm::new(program)?;
+    vm.edit_program(0, 2)?;
 
-    let screen = vm.drain();
-    let dust = screen.last().ok_or("No output")?;
+    let mut robot = Ascii::new(vm);
+    rules.iter().for_each(|rule| robot.write_line(rule));
+    robot.write_line("n"); // feed
+    robot.exec()?;
 
-    Ok(*dust)
+    let (_, dust) = robot.output();
+    dust.ok_or_else(|| anyhow!("No output"))
 }
 
-#[allow(dead_code)]
-fn draw(screen: &[i64]) {
-    let s: String = screen.iter().map(|b| (*b as u8) as char).collect();
-    println!("{s}");
+fn camera(program: &str) -> Result<View> {
+    let mut camera = Ascii::new(Vm::new(program)?);
+    camera.exec()?;
+
+    View::from(&camera.read_text())
 }
 
-#[allow(dead_code)]
-fn print_screen(
-    rows: i32,
-    cols: i32,
-    robot: &(i32, i32),
+struct View {
+    skaffold: Skaffold,
+    robot: (i32, i32),
     facing: Direction,
-    skaffold: &Skaffold
-) {
-    for y in 0..rows {
-        for x in 0..cols {
-            let pos = (x, y);
-            let c = if skaffold.contains(&pos) {
-                if is_intersection(&pos, skaffold) { 'O' } else { '#' }
-            } else if robot == &pos {
-                facing_char(facing)
-            } else {
-                '.'
-            };
-            print!("{c}");
+}
+
+impl View {
+    fn from(image: &str) -> Result<Self> {
+        let mut skaffold = HashSet::new();
+        let mut robot = None;
+        for (y, row) in image.lines().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let pos = (x as i32, y as i32);
+                let facing = match c {
+                    '#' => { skaffold.insert(pos); continue },
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    _   => continue,
+                };
+                skaffold.insert(pos);
+                robot = Some((pos, facing));
+            }
+        }
+
+        let (robot, facing) = robot.ok_or_else(|| anyhow!("No robot in image"))?;
+        Ok(View { skaffold, robot, facing })
+    }
+}
+
+/// Follow the scaffold from the robot, turning only at the ends of
+/// straight runs. Each move is a turn and a distance, e.g. "L,12",
+/// apart from the first, which can go straight on ("12") or turn
+/// round ("R,R,12") if that's where the scaffold starts. A scaffold
+/// that loops back on itself is walked round once.
+fn walk(view: &View) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pos = view.robot;
+    let mut facing = view.facing;
+    let open = |pos, dir| view.skaffold.contains(&ahead(pos, dir));
+    loop {
+        let start = path.is_empty();
+        let (turn, dir) = if start && open(pos, facing) {
+            ("", facing)
+        } else if open(pos, facing.left()) {
+            ("L,", facing.left())
+        } else if open(pos, facing.right()) {
+            ("R,", facing.right())
+        } else if start && open(pos, facing.left().left()) {
+            ("R,R,", facing.left().left())
+        } else {
+            return path
+        };
+        if !seen.insert((pos, dir)) {
+            return path
+        }
+
+        let mut steps = 0;
+        while open(pos, dir) {
+            pos = ahead(pos, dir);
+            steps += 1;
+        }
+        path.push(format!("{turn}{steps}"));
+        facing = dir;
+    }
+}
+
+fn ahead(pos: (i32, i32), dir: Direction) -> (i32, i32) {
+    use Direction::*;
+
+    match dir {
+        Up    => (pos.0, pos.1 - 1),
+        Down  => (pos.0, pos.1 + 1),
+        Left  => (pos.0 - 1, pos.1),
+        Right => (pos.0 + 1, pos.1),
+    }
+}
+
+// Characters of robot memory per rule, not counting the newline
+const MEMORY: usize = 20;
+
+/// Split the path into a main routine and three movement functions,
+/// all of which fit in the robot's memory.
+fn compress(path: &[String]) -> Option<[String; 4]> {
+    if path.is_empty() {
+        return None
+    }
+
+    let mut funcs = Vec::new();
+    let mut main = Vec::new();
+    if !split(path, &mut funcs, &mut main) {
+        return None
+    }
+
+    // The robot wants all three functions, so any it doesn't call
+    // repeat the first.
+    let rule = |i: usize| funcs.get(i).unwrap_or(&funcs[0]).join(",");
+    Some([main.join(","), rule(0), rule(1), rule(2)])
+}
+
+fn split<'a>(
+    path: &'a [String],
+    funcs: &mut Vec<&'a [String]>,
+    main: &mut Vec<&'static str>
+) -> bool {
+    const NAMES: [&str; 3] = ["A", "B", "C"];
+
+    if path.is_empty() {
+        return true
+    }
+    if main.len() * 2 + 1 > MEMORY {
+        return false
+    }
+
+    for i in 0..funcs.len() {
+        if path.starts_with(funcs[i]) {
+            main.push(NAMES[i]);
+            if split(&path[funcs[i].len()..], funcs, main) {
+                return true
+            }
+            main.pop();
         }
-        println!();
     }
+
+    if funcs.len() < NAMES.len() {
+        for n in 1..=path.len() {
+            if path[..n].join(",").len() > MEMORY {
+                break
+            }
+            funcs.push(&path[..n]);
+            main.push(NAMES[funcs.len() - 1]);
+            if split(&path[n..], funcs, main) {
+                return true
+            }
+            main.pop();
+            funcs.pop();
+        }
+    }
+
+    false
+}
+
+#[allow(dead_code)]
+fn print_screen(view: &View) {
+    let mut screen = Screen::new();
+    for pos in &view.skaffold {
+        let c = if is_intersection(pos, &view.skaffold) { 'O' } else { '#' };
+        screen.set(pos.0 as i64, pos.1 as i64, c as i64);
+    }
+    screen.set(view.robot.0 as i64, view.robot.1 as i64, facing_char(view.facing) as i64);
+
+    print!("{}", screen.render(|v| v.map_or('.', |c| c as u8 as char)));
 }
 
 #[allow(dead_code)]
@@ -121,7 +226,7 @@ fn is_intersection(pos: &(i32, i32), skaffold: &Skaffold) -> bool {
     skaffold.contains(&(pos.0 + 1, pos.1))     // after
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     Up,
     Down,
@@ -129,6 +234,23 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn left(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Up    => Left,
+            Left  => Down,
+            Down  => Right,
+            Right => Up,
+        }
+    }
+
+    fn right(self) -> Self {
+        self.left().left().left()
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -144,4 +266,104 @@ mod tests {
         let dust = part_two(program).unwrap();
         assert_eq!(dust, 923017);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn movement() {
+        let image = "\
+            #######...#####\n\
+            #.....#...#...#\n\
+            #.....#...#...#\n\
+            ......#...#...#\n\
+            ......#...###.#\n\
+            ......#.....#.#\n\
+            ^########...#.#\n\
+            ......#.#...#.#\n\
+            ......#########\n\
+            ........#...#..\n\
+            ....#########..\n\
+            ....#...#......\n\
+            ....#...#......\n\
+            ....#...#......\n\
+            ....#####......\n";
+
+        let path = walk(&View::from(image).unwrap());
+        assert_eq!(
+            path.join(","),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+
+        let rules = compress(&path).unwrap();
+        let expanded = rules[0].split(',')
+            .map(|f| match f {
+                "A" => &rules[1],
+                "B" => &rules[2],
+                _   => &rules[3],
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(expanded.join(","), path.join(","));
+        assert!(rules.iter().all(|r| r.len() <= MEMORY));
+    }
+
+    #[test]
+    fn unused_functions() {
+        let path = ["R,8", "R,8", "R,8"].map(String::from);
+        let rules = compress(&path).unwrap();
+        assert_eq!(rules, ["A,A,A", "R,8", "R,8", "R,8"].map(String::from));
+    }
+
+    #[test]
+    fn screen() {
+        // The camera image with intersections marked O, then notes
+        let (image, _) = include_str!("./screen.txt").split_once("\n\n").unwrap();
+        let view = View::from(&image.replace('O', "#")).unwrap();
+        let calibration: i32 = view.skaffold.iter()
+            .filter(|p| is_intersection(p, &view.skaffold))
+            .map(|p| p.0 * p.1)
+            .sum();
+        assert_eq!(calibration, 6672);
+
+        let path = walk(&view);
+        assert_eq!(path.join(",").replace(',', ""), concat!(
+            "L12R4R4R12R4L12R12R4L12R12R4L6L8L8R12R4L6L8L8",
+            "L12R4R4L12R4R4R12R4L12R12R4L12R12R4L6L8L8"
+        ));
+        let rules = compress(&path).unwrap();
+        assert!(rules.iter().all(|r| r.len() <= MEMORY));
+    }
+
+    #[test]
+    fn first_move() {
+        let image = "\
+            ..#####\n\
+            ..#...#\n\
+            ..^...#\n\
+            ......#\n\
+            ..#####\n";
+
+        // Facing along the scaffold
+        let path = walk(&View::from(image).unwrap());
+        assert_eq!(path.join(","), "2,R,4,R,4,R,4");
+        assert!(compress(&path).is_some());
+
+        // Facing away from it
+        let path = walk(&View::from(&image.replace('^', "v")).unwrap());
+        assert_eq!(path.join(","), "R,R,2,R,4,R,4,R,4");
+        assert!(compress(&path).is_some());
+
+        // Round a loop once
+        let image = "\
+            #####\n\
+            #...#\n\
+            #...#\n\
+            #...#\n\
+            ^####\n";
+        let path = walk(&View::from(image).unwrap());
+        assert_eq!(path.join(","), "4,R,4,R,4,R,4");
+
+        // Nowhere to go
+        let path = walk(&View::from("...\n.^.\n...\n").unwrap());
+        assert!(path.is_empty());
+        assert!(compress(&path).is_none());
+    }
+}
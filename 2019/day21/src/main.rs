@@ -1,59 +1,74 @@
-use vm::Vm;
+use vm::{ascii::Ascii, Vm};
+
+mod springscript;
+use springscript::{Expr, Mode, Program};
 
 fn main() {
   let program = include_str!("./program.txt");
 
+  let args: Vec<_> = std::env::args().collect();
+  if let Some(i) = args.iter().position(|arg| arg == "--rules") {
+    let text = std::fs::read_to_string(&args[i + 1]).unwrap();
+    match Program::parse(&text).and_then(|rules| survey(program, &rules)) {
+      Ok(damage) => println!("Damage: {damage}"),
+      Err(e) => println!("{e}"),
+    }
+    return
+  }
+  if args.iter().any(|arg| arg == "--search") {
+    for mode in [Mode::Walk, Mode::Run] {
+      match search(program, mode) {
+        Ok((expr, damage)) => println!("{mode:?}: {damage}\n{expr:?}"),
+        Err(e) => println!("{mode:?}: {e}"),
+      }
+    }
+    return
+  }
+
   println!("Part 1: {}", part_one(program));
   println!("Part 2: {}", part_two(program));
 }
 
 fn part_one(program: &str) -> i64 {
-  let rules = b"\
-NOT C J
-AND D J
-NOT A T
-OR T J 
-WALK
-";
-
-  let mut vm = Vm::new(program).unwrap();
-
-  rules.iter().for_each(|&v| vm.write(v as i64));
-  vm.exec().unwrap();
-
-  let screen = vm.drain();
-  let dust = screen.last().unwrap();
+  let expr = Expr::parse("!C & D | !A").unwrap();
+  let rules = Program::compile(&expr, Mode::Walk).unwrap();
 
-  *dust
+  survey(program, &rules).unwrap()
 }
 
 fn part_two(program: &str) -> i64 {
-  let rules = b"\
-NOT H T
-OR C T
-AND B T
-AND A T
-NOT T J
-AND D J
-RUN
-";
+  let expr = Expr::parse("!((!H | C) & B & A) & D").unwrap();
+  let rules = Program::compile(&expr, Mode::Run).unwrap();
 
-  let mut vm = Vm::new(program).unwrap();
-
-  rules.iter().for_each(|&v| vm.write(v as i64));
-  vm.exec().unwrap();
+  survey(program, &rules).unwrap()
+}
 
-  let screen = vm.drain();
-  let dust = screen.last().unwrap();
+/// Try candidate formulas until the springdroid makes it across,
+/// returning the first that does and the hull damage.
+fn search(program: &str, mode: Mode) -> Result<(Expr, i64), String> {
+  for expr in springscript::candidates(mode) {
+    let rules = match Program::compile(&expr, mode) {
+      Ok(rules) => rules,
+      Err(_) => continue,
+    };
+    if let Ok(damage) = survey(program, &rules) {
+      return Ok((expr, damage))
+    }
+  }
 
-  *dust
+  Err("no candidate survived".to_string())
 }
 
+/// Run the springdroid, returning the hull damage or, if it fell into
+/// space, the rendering of its last moments.
+fn survey(program: &str, rules: &Program) -> Result<i64, String> {
+  let mut droid = Ascii::new(Vm::new(program).map_err(|e| e.to_string())?);
+
+  rules.to_string().lines().for_each(|line| droid.write_line(line));
+  droid.exec().map_err(|e| e.to_string())?;
 
-#[allow(dead_code)]
-fn draw(screen: &[i64]) {
-  let s: String = screen.iter().map(|b| (*b as u8) as char).collect();
-  println!("{s}");
+  let (text, damage) = droid.output();
+  damage.ok_or(text)
 }
 
 
@@ -70,5 +85,8 @@ mod tests {
 
     let damage = part_two(program);
     assert_eq!(damage, 1141685254);
+
+    let (_, damage) = search(program, Mode::Walk).unwrap();
+    assert_eq!(damage, 19349722);
   }
-}
\ No newline at end of file
+}
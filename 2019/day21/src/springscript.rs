@@ -0,0 +1,339 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Instructions the springdroid can hold, not counting WALK or RUN.
+pub const LIMIT: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+  Walk,
+  Run,
+}
+
+impl Mode {
+  /// Sensors readable in this mode, nearest first.
+  pub fn sensors(self) -> &'static str {
+    match self {
+      Mode::Walk => "ABCD",
+      Mode::Run  => "ABCDEFGHI",
+    }
+  }
+}
+
+/// A boolean formula over the sensors deciding whether to jump.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+  Sensor(char),
+  Not(Box<Expr>),
+  And(Box<Expr>, Box<Expr>),
+  Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+  /// Parse a formula such as `!(A & B & C) & D`. `!` binds tighter
+  /// than `&`, which binds tighter than `|`.
+  pub fn parse(s: &str) -> Result<Self, String> {
+    let mut chars = s.chars().peekable();
+    let expr = parse_or(&mut chars)?;
+    match next(&mut chars) {
+      None => Ok(expr),
+      Some(c) => Err(format!("unexpected '{c}' in {s}")),
+    }
+  }
+
+  /// Whether it can be loaded into a register without the other one.
+  fn is_flat(&self) -> bool {
+    match self {
+      Expr::Sensor(_) => true,
+      Expr::Not(e) => e.is_flat(),
+      Expr::And(a, b) | Expr::Or(a, b) => match (&**a, &**b) {
+        (e, Expr::Sensor(_)) | (Expr::Sensor(_), e) => e.is_flat(),
+        _ => false,
+      },
+    }
+  }
+}
+
+fn next(chars: &mut Peekable<Chars>) -> Option<char> {
+  while chars.next_if(|c| c.is_whitespace()).is_some() {}
+  chars.next()
+}
+
+fn peek(chars: &mut Peekable<Chars>) -> Option<char> {
+  while chars.next_if(|c| c.is_whitespace()).is_some() {}
+  chars.peek().copied()
+}
+
+fn parse_or(chars: &mut Peekable<Chars>) -> Result<Expr, String> {
+  let mut expr = parse_and(chars)?;
+  while peek(chars) == Some('|') {
+    chars.next();
+    expr = Expr::Or(Box::new(expr), Box::new(parse_and(chars)?));
+  }
+
+  Ok(expr)
+}
+
+fn parse_and(chars: &mut Peekable<Chars>) -> Result<Expr, String> {
+  let mut expr = parse_not(chars)?;
+  while peek(chars) == Some('&') {
+    chars.next();
+    expr = Expr::And(Box::new(expr), Box::new(parse_not(chars)?));
+  }
+
+  Ok(expr)
+}
+
+fn parse_not(chars: &mut Peekable<Chars>) -> Result<Expr, String> {
+  match next(chars) {
+    Some('!') => Ok(Expr::Not(Box::new(parse_not(chars)?))),
+    Some('(') => {
+      let expr = parse_or(chars)?;
+      match next(chars) {
+        Some(')') => Ok(expr),
+        _ => Err("missing ')'".to_string()),
+      }
+    },
+    Some(c @ 'A'..='I') => Ok(Expr::Sensor(c)),
+    Some(c) => Err(format!("unknown sensor '{c}'")),
+    None => Err("unexpected end of formula".to_string()),
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+  And,
+  Or,
+  Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inst {
+  pub op: Op,
+  pub x: char,
+  pub y: char,
+}
+
+impl fmt::Display for Inst {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let op = match self.op {
+      Op::And => "AND",
+      Op::Or  => "OR",
+      Op::Not => "NOT",
+    };
+    write!(f, "{op} {} {}", self.x, self.y)
+  }
+}
+
+/// A validated springscript program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+  insts: Vec<Inst>,
+  mode: Mode,
+}
+
+impl Program {
+  pub fn new(insts: Vec<Inst>, mode: Mode) -> Result<Self, String> {
+    if insts.len() > LIMIT {
+      return Err(format!("{} instructions, the limit is {LIMIT}", insts.len()))
+    }
+    for inst in &insts {
+      if !mode.sensors().contains(inst.x) && !is_register(inst.x) {
+        return Err(format!("invalid input register in '{inst}'"))
+      }
+      if !is_register(inst.y) {
+        return Err(format!("invalid output register in '{inst}'"))
+      }
+    }
+
+    Ok(Program { insts, mode })
+  }
+
+  /// Parse a listing ending in WALK or RUN.
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mode = match lines.next_back() {
+      Some("WALK") => Mode::Walk,
+      Some("RUN")  => Mode::Run,
+      _ => return Err("program must end with WALK or RUN".to_string()),
+    };
+
+    let insts = lines
+      .map(|line| {
+        let parts: Vec<_> = line.split_whitespace().collect();
+        let op = match parts[0] {
+          "AND" => Op::And,
+          "OR"  => Op::Or,
+          "NOT" => Op::Not,
+          op => return Err(format!("unknown instruction '{op}'")),
+        };
+        match parts[1..] {
+          [x, y] if x.len() == 1 && y.len() == 1 => {
+            let x = x.chars().next().unwrap();
+            let y = y.chars().next().unwrap();
+            Ok(Inst { op, x, y })
+          },
+          _ => Err(format!("malformed instruction '{line}'")),
+        }
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Program::new(insts, mode)
+  }
+
+  /// Compile a formula so the droid jumps when it holds.
+  pub fn compile(expr: &Expr, mode: Mode) -> Result<Self, String> {
+    let mut compiler = Compiler { insts: Vec::new(), clean: [true, true] };
+    compiler.load(expr, 'J')?;
+    Program::new(compiler.insts, mode)
+  }
+}
+
+impl fmt::Display for Program {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.insts.iter().try_for_each(|inst| writeln!(f, "{inst}"))?;
+    match self.mode {
+      Mode::Walk => write!(f, "WALK"),
+      Mode::Run  => write!(f, "RUN"),
+    }
+  }
+}
+
+fn is_register(c: char) -> bool {
+  c == 'T' || c == 'J'
+}
+
+fn other(r: char) -> char {
+  if r == 'T' { 'J' } else { 'T' }
+}
+
+struct Compiler {
+  insts: Vec<Inst>,
+  // T and J start out false, which saves loading a sensor
+  clean: [bool; 2],
+}
+
+impl Compiler {
+  fn emit(&mut self, op: Op, x: char, y: char) {
+    self.insts.push(Inst { op, x, y });
+    self.clean[(y == 'J') as usize] = false;
+  }
+
+  /// Leave the value of `expr` in register `r`, using the other
+  /// register as scratch.
+  fn load(&mut self, expr: &Expr, r: char) -> Result<(), String> {
+    match expr {
+      Expr::Sensor(s) => if self.clean[(r == 'J') as usize] {
+        self.emit(Op::Or, *s, r)
+      } else {
+        self.emit(Op::Not, *s, r);
+        self.emit(Op::Not, r, r);
+      },
+      Expr::Not(e) => match &**e {
+        Expr::Sensor(s) => self.emit(Op::Not, *s, r),
+        Expr::Not(e) => self.load(e, r)?,
+        e => {
+          self.load(e, r)?;
+          self.emit(Op::Not, r, r);
+        },
+      },
+      Expr::And(a, b) | Expr::Or(a, b) => {
+        let op = if let Expr::And(..) = expr { Op::And } else { Op::Or };
+        let (first, second) = match (&**a, &**b) {
+          (_, Expr::Sensor(_)) => (a, b),
+          (Expr::Sensor(_), _) => (b, a),
+          _ if b.is_flat() => (a, b),
+          _ if a.is_flat() => (b, a),
+          _ => return Err("formula needs more than two registers".to_string()),
+        };
+
+        self.load(first, r)?;
+        match &**second {
+          Expr::Sensor(s) => self.emit(op, *s, r),
+          e => {
+            self.load(e, other(r))?;
+            self.emit(op, other(r), r);
+          },
+        }
+      },
+    }
+
+    Ok(())
+  }
+}
+
+/// Formulas to try when searching for a surviving program, smallest
+/// first: jump when there's a hole in the next three tiles and ground
+/// four away, and when running, somewhere to go after landing.
+pub fn candidates(mode: Mode) -> Vec<Expr> {
+  let near = subsets("ABC");
+  let far = match mode {
+    Mode::Walk => vec![vec![]],
+    Mode::Run  => subsets("EFGHI"),
+  };
+
+  let mut formulas: Vec<_> = near.iter()
+    .filter(|n| !n.is_empty())
+    .flat_map(|n| far.iter().map(move |f| (n, f)))
+    .map(|(n, f)| {
+      let mut s = format!("!({}) & D", n.join(" & "));
+      if !f.is_empty() {
+        s += &format!(" & ({})", f.join(" | "));
+      }
+      (n.len() + f.len(), s)
+    })
+    .collect();
+  formulas.sort();
+
+  formulas.iter()
+    .map(|(_, s)| Expr::parse(s).unwrap())
+    .collect()
+}
+
+fn subsets(sensors: &str) -> Vec<Vec<String>> {
+  let sensors: Vec<_> = sensors.chars().collect();
+  (0..1 << sensors.len())
+    .map(|mask| sensors.iter()
+      .enumerate()
+      .filter(|(i, _)| mask & (1 << i) != 0)
+      .map(|(_, c)| c.to_string())
+      .collect()
+    )
+    .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compiles() {
+    let expr = Expr::parse("!C & D | !A").unwrap();
+    let program = Program::compile(&expr, Mode::Walk).unwrap();
+    assert_eq!(program.to_string(), "NOT C J\nAND D J\nNOT A T\nOR T J\nWALK");
+
+    let expr = Expr::parse("!(A & B & C) & D & (E | H)").unwrap();
+    let program = Program::compile(&expr, Mode::Run).unwrap();
+    assert_eq!(Program::parse(&program.to_string()), Ok(program));
+  }
+
+  #[test]
+  fn validates() {
+    assert!(Expr::parse("A & X").is_err());
+    assert!(Expr::parse("(A | B").is_err());
+
+    let expr = Expr::parse("E & D").unwrap();
+    assert!(Program::compile(&expr, Mode::Walk).is_err());
+    assert!(Program::compile(&expr, Mode::Run).is_ok());
+
+    let expr = Expr::parse("(A | B) & (C | D) | (E | F) & (G | H)").unwrap();
+    assert!(Program::compile(&expr, Mode::Run).is_err());
+
+    assert!(Program::parse("NOT A B\nWALK").is_err());
+    assert!(Program::parse("NOT E J\nWALK").is_err());
+    assert!(Program::parse("NOT A J").is_err());
+    assert!(Program::parse(&"OR A J\n".repeat(16)).is_err());
+  }
+}
@@ -1,9 +1,6 @@
-use std::collections::HashMap;
+use vm::net::{Network, Schedule};
 use vm::Vm;
 
-type Network = HashMap<i64, Vm>;
-type Packets = HashMap<i64, Vec<(i64, i64)>>;
-
 fn main() {
     let program = include_str!("./nic.txt");
 
@@ -12,78 +9,69 @@ fn main() {
 }
 
 fn part_one(nic: &str) -> i64 {
-    let mut packets = HashMap::new();
     let mut network = setup_network(nic);
     loop {
-        for vm in network.values_mut() {
-            vm.cont().unwrap();
-            if let Some(nic) = vm.read() {
-                let x = vm.read().unwrap();
-                let y = vm.read().unwrap();
-                if nic == 255 {
-                    return y
-                }
-                packets.entry(nic).or_insert_with(Vec::new).push((x, y));
+        network.run(Schedule::Cooperative).unwrap();
+        for (nic, x, y) in packets(&mut network) {
+            if nic == 255 {
+                return y
             }
-        };
-        process_packets(&mut packets, &mut network);
+            send(&mut network, nic, x, y);
+        }
     }
 }
 
 fn part_two(nic: &str) -> i64 {
     let mut nat = (0, 0);
     let mut last_y = -1;
-    let mut packets = HashMap::new();
     let mut network = setup_network(nic);
     loop {
-        for vm in network.values_mut() {
-            vm.cont().unwrap();
-            if let Some(nic) = vm.read() {
-                let x = vm.read().unwrap();
-                let y = vm.read().unwrap();
-                if nic == 255 {
-                    nat = (x, y);
-                } else {
-                    packets.entry(nic).or_insert_with(Vec::new).push((x, y));
-                }
+        network.run(Schedule::Threaded(4)).unwrap();
+        let mut idle = true;
+        for (nic, x, y) in packets(&mut network) {
+            if nic == 255 {
+                nat = (x, y);
+            } else {
+                idle = false;
+                send(&mut network, nic, x, y);
             }
-        };
-        if packets.is_empty() {
+        }
+        if idle {
             if last_y == nat.1 {
                 return last_y
             } else {
                 last_y = nat.1
             }
-
-            let vm = network.get_mut(&0).unwrap();
-            vm.write(nat.0);
-            vm.write(nat.1);
+            send(&mut network, 0, nat.0, nat.1);
         }
-        process_packets(&mut packets, &mut network);
     }
 }
 
 fn setup_network(nic: &str) -> Network {
-    (0..50)
-        .map(|n| (n, Vm::new(nic).unwrap()))
-        .map(|(n, mut vm)| {
-            vm.exec().unwrap();
-            vm.write(n);
-            (n, vm)
-        })
+    let mut network = Network::new();
+    network.set_idle_input(Some(-1));
+    for n in 0..50 {
+        let mut vm = Vm::new(nic).unwrap();
+        vm.write(n);
+        network.add(vm);
+    }
+
+    network
+}
+
+fn packets(network: &mut Network) -> Vec<(i64, i64, i64)> {
+    network.iter_mut()
+        .flat_map(|vm| vm.drain())
+        .collect::<Vec<_>>()
+        .chunks(3)
+        .map(|p| (p[0], p[1], p[2]))
         .collect()
 }
 
-fn process_packets(packets: &mut Packets, network: &mut Network) {
-    network.iter_mut().for_each(|(nic, vm)| {
-        match packets.remove(nic) {
-            Some(v) => v.iter().for_each(|&(x, y)| {
-                vm.write(x);
-                vm.write(y);
-            }),
-            None => vm.write(-1),
-        }
-    });
+fn send(network: &mut Network, nic: i64, x: i64, y: i64) {
+    let vm = network.vm_mut(nic as usize);
+    vm.write(x);
+    vm.write(y);
 }
 
 
@@ -1,29 +1,232 @@
-// Password: 8462464
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use vm::{ascii::Ascii, repl::Repl, State, Vm};
+
+// Instructions allowed per command before the droid is deemed stuck
+const BUDGET: usize = 1_000_000;
 
 fn main() {
   use std::io;
-  use vm::Vm;
 
   let program = include_str!("./program.txt");
-  let mut vm = Vm::new(program).unwrap();
-  vm.exec().unwrap();
-
-  loop {
-    let prompt: String = vm.drain().iter().map(|v| (*v as u8) as char).collect();
-    print!("{prompt}");
-
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf).unwrap();
-    let cmd = match buf.as_str() {
-      "n\n" => "north\n",
-      "s\n" => "south\n",
-      "e\n" => "east\n",
-      "w\n" => "west\n",
-      "q\n" => break,
-      "quit\n" => break,
-      _ => &buf
-    };
-    cmd.as_bytes().iter().for_each(|&b| vm.write(b));
-    vm.cont().unwrap();
+  if std::env::args().any(|arg| arg == "--play") {
+    Repl::new(Vm::new(program).unwrap())
+      .ascii()
+      .alias("n", "north")
+      .alias("s", "south")
+      .alias("e", "east")
+      .alias("w", "west")
+      .alias("q", ".q")
+      .alias("quit", ".q")
+      .run(io::stdin().lock(), io::stdout())
+      .unwrap();
+  } else {
+    println!("Password: {}", solve(program).unwrap());
+  }
+}
+
+fn solve(program: &str) -> Result<String> {
+  let mut droid = Droid { io: Ascii::new(Vm::new(program)?) };
+  let room = match droid.run()? {
+    Outcome::Text(text) => parse(&text).pop().ok_or_else(|| anyhow!("no start room"))?,
+    _ => return Err(anyhow!("droid failed to start")),
   };
+
+  let mut ship = Ship::default();
+  droid.explore(&room, None, &mut vec![], &mut ship)?;
+
+  let (path, door) = ship.checkpoint.ok_or_else(|| anyhow!("no pressure-sensitive floor"))?;
+  for dir in &path {
+    droid.command(dir)?;
+  }
+  droid.crack(&ship.items, &door)
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+  /// Droid is waiting for a command.
+  Text(String),
+  Halted(String),
+  Looping,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Room {
+  name: String,
+  doors: Vec<String>,
+  items: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Ship {
+  visited: HashSet<String>,
+  items: Vec<String>,
+  // Path to the security checkpoint and its door to the floor
+  checkpoint: Option<(Vec<String>, String)>,
+}
+
+struct Droid {
+  io: Ascii,
+}
+
+impl Droid {
+  fn command(&mut self, cmd: &str) -> Result<Outcome> {
+    self.io.write_line(cmd);
+    self.run()
+  }
+
+  fn run(&mut self) -> Result<Outcome> {
+    for _ in 0..BUDGET {
+      match self.io.vm_mut().step()? {
+        State::Running => continue,
+        State::Done => return Ok(Outcome::Halted(self.io.read_text())),
+        _ => return Ok(Outcome::Text(self.io.read_text())),
+      }
+    }
+
+    Ok(Outcome::Looping)
+  }
+
+  /// Depth first walk of the ship picking up every safe item. Returns
+  /// the droid to the room it started in.
+  fn explore(
+    &mut self,
+    room: &Room,
+    from: Option<&str>,
+    path: &mut Vec<String>,
+    ship: &mut Ship
+  ) -> Result<()> {
+    ship.visited.insert(room.name.clone());
+    for item in &room.items {
+      if self.is_safe(item, &room.doors)? {
+        self.command(&format!("take {item}"))?;
+        ship.items.push(item.clone());
+      }
+    }
+
+    for door in &room.doors {
+      if Some(door.as_str()) == from {
+        continue
+      }
+
+      let rooms = match self.command(door)? {
+        Outcome::Text(text) => parse(&text),
+        _ => return Err(anyhow!("lost the droid going {door} from {}", room.name)),
+      };
+      match rooms.as_slice() {
+        [next] if !ship.visited.contains(&next.name) => {
+          path.push(door.clone());
+          self.explore(next, Some(opposite(door)), path, ship)?;
+          path.pop();
+          self.command(opposite(door))?;
+        },
+        [_] => { self.command(opposite(door))?; },
+        // Turned back by the security checkpoint
+        _ => ship.checkpoint = Some((path.clone(), door.clone())),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Taking a trap item kills the droid, hangs the program or stops
+  /// the droid from moving.
+  fn is_safe(&mut self, item: &str, doors: &[String]) -> Result<bool> {
+    let saved = self.io.vm().snapshot();
+    let safe = match self.command(&format!("take {item}"))? {
+      Outcome::Text(_) => match self.command(&doors[0])? {
+        Outcome::Text(text) => !parse(&text).is_empty(),
+        _ => false,
+      },
+      _ => false,
+    };
+    self.io.vm_mut().restore(&saved);
+
+    Ok(safe)
+  }
+
+  /// Try every subset of the items on the pressure-sensitive floor.
+  fn crack(&mut self, items: &[String], door: &str) -> Result<String> {
+    let saved = self.io.vm().snapshot();
+    for mask in 0..1u32 << items.len() {
+      self.io.vm_mut().restore(&saved);
+      for (i, item) in items.iter().enumerate() {
+        if mask & (1 << i) == 0 {
+          self.command(&format!("drop {item}"))?;
+        }
+      }
+      if let Outcome::Halted(text) = self.command(door)? {
+        return password(&text).ok_or_else(|| anyhow!("no password in: {text}"))
+      }
+    }
+
+    Err(anyhow!("no combination of {} items is accepted", items.len()))
+  }
+}
+
+fn parse(text: &str) -> Vec<Room> {
+  let mut rooms = Vec::new();
+  let mut list = None;
+  for line in text.lines().map(str::trim) {
+    if let Some(name) = line.strip_prefix("== ").and_then(|s| s.strip_suffix(" ==")) {
+      rooms.push(Room { name: name.to_string(), ..Room::default() });
+    } else if line == "Doors here lead:" {
+      list = Some(true);
+    } else if line == "Items here:" {
+      list = Some(false);
+    } else if let (Some(entry), Some(room)) = (line.strip_prefix("- "), rooms.last_mut()) {
+      match list {
+        Some(true)  => room.doors.push(entry.to_string()),
+        Some(false) => room.items.push(entry.to_string()),
+        None => {},
+      }
+    } else {
+      list = None;
+    }
+  }
+
+  rooms
+}
+
+fn opposite(dir: &str) -> &'static str {
+  match dir {
+    "north" => "south",
+    "south" => "north",
+    "east"  => "west",
+    _       => "east",
+  }
+}
+
+fn password(text: &str) -> Option<String> {
+  text.split(|c: char| !c.is_ascii_digit())
+    .max_by_key(|s| s.len())
+    .filter(|s| !s.is_empty())
+    .map(String::from)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_works() {
+    let program = include_str!("./program.txt");
+    assert_eq!(solve(program).unwrap(), "8462464");
+  }
+
+  #[test]
+  fn rooms() {
+    let text = "\n\n\n== Kitchen ==\nEverything's freeze-dried.\n\n\
+                Doors here lead:\n- north\n- east\n- south\n\n\
+                Items here:\n- space heater\n\nCommand?\n";
+    let room = Room {
+      name: "Kitchen".to_string(),
+      doors: vec!["north".to_string(), "east".to_string(), "south".to_string()],
+      items: vec!["space heater".to_string()],
+    };
+
+    assert_eq!(parse(text), [room]);
+    assert_eq!(password("typing 8462464 on the keypad"), Some("8462464".to_string()));
+  }
 }
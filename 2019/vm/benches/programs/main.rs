@@ -0,0 +1,221 @@
+//! Timings for the day programs, run on both the current vm and the
+//! one it replaced (see baseline.rs) so each group shows the two side
+//! by side. Each workload loads its day's program at run time; only
+//! boost.txt and nic.txt are committed, so the others report their
+//! file as missing until it's dropped in.
+
+use std::fs;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod baseline;
+
+/// What the workloads need from a vm, so they run unchanged on both.
+trait Machine {
+    fn load(program: &str) -> Self;
+    fn write(&mut self, v: i64);
+    fn read(&mut self) -> Option<i64>;
+    fn drain(&mut self) -> Vec<i64>;
+    fn exec(&mut self);
+    fn cont(&mut self);
+    fn edit_program(&mut self, addr: usize, v: i64);
+}
+
+impl Machine for vm::Vm {
+    fn load(program: &str) -> Self { vm::Vm::new(program).unwrap() }
+    fn write(&mut self, v: i64) { vm::Vm::write(self, v) }
+    fn read(&mut self) -> Option<i64> { vm::Vm::read(self) }
+    fn drain(&mut self) -> Vec<i64> { vm::Vm::drain(self) }
+    fn exec(&mut self) { vm::Vm::exec(self).unwrap(); }
+    fn cont(&mut self) { vm::Vm::cont(self).unwrap(); }
+    fn edit_program(&mut self, addr: usize, v: i64) { vm::Vm::edit_program(self, addr, v).unwrap() }
+}
+
+impl Machine for baseline::Vm {
+    fn load(program: &str) -> Self { baseline::Vm::new(program.trim()).unwrap() }
+    fn write(&mut self, v: i64) { baseline::Vm::write(self, v) }
+    fn read(&mut self) -> Option<i64> { baseline::Vm::read(self) }
+    fn drain(&mut self) -> Vec<i64> { baseline::Vm::drain(self) }
+    fn exec(&mut self) { baseline::Vm::exec(self).unwrap(); }
+    fn cont(&mut self) { baseline::Vm::cont(self).unwrap(); }
+    fn edit_program(&mut self, addr: usize, v: i64) { baseline::Vm::edit_program(self, addr, v) }
+}
+
+type Workload = fn(&str);
+
+enum Source {
+    /// A file in the day's src directory
+    File(&'static str, &'static str),
+    Inline(&'static str),
+}
+
+// Day 5's "is the input 8" example
+const SHORT: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+
+fn workloads<M: Machine>() -> [(&'static str, Source, Workload); 10] {
+    use Source::*;
+    [
+        ("day02", File("day02", "program.txt"), gravity_assist::<M>),
+        ("day05", File("day05", "program.txt"), diagnostics::<M>),
+        ("day07", File("day07", "program.txt"), amplifiers::<M>),
+        ("day09", File("day09", "boost.txt"), boost::<M>),
+        ("day13", File("day13", "program.txt"), arcade::<M>),
+        ("day17", File("day17", "program.txt"), camera::<M>),
+        ("day19", File("day19", "program.txt"), tractor_beam::<M>),
+        ("day21", File("day21", "program.txt"), springdroid::<M>),
+        ("day23", File("day23", "nic.txt"), nics::<M>),
+        ("short", Inline(SHORT), short::<M>),
+    ]
+}
+
+fn gravity_assist<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    vm.edit_program(1, 12);
+    vm.edit_program(2, 2);
+    vm.exec();
+}
+
+fn diagnostics<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    vm.write(5);
+    vm.exec();
+}
+
+fn amplifiers<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    let mut signal = 0;
+    for phase in 0..5 {
+        vm.write(phase);
+        vm.write(signal);
+        vm.exec();
+        signal = vm.read().unwrap();
+    }
+}
+
+// Five runs on one vm, so resetting to the program counts too
+fn boost<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    for _ in 0..5 {
+        vm.write(2);
+        vm.exec();
+        vm.drain();
+    }
+}
+
+fn arcade<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    vm.exec();
+}
+
+fn camera<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    vm.exec();
+}
+
+// One exec per position, as day 19 scans the beam
+fn tractor_beam<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    for y in 0..50 {
+        for x in 0..50 {
+            vm.write(x);
+            vm.write(y);
+            vm.exec();
+            vm.drain();
+        }
+    }
+}
+
+fn springdroid<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    "NOT C J\nAND D J\nNOT A T\nOR T J\nWALK\n".bytes()
+        .for_each(|b| vm.write(b.into()));
+    vm.exec();
+}
+
+// Almost all reset: a handful of instructions per exec
+fn short<M: Machine>(program: &str) {
+    let mut vm = M::load(program);
+    for i in 0..2000 {
+        vm.write(i % 16);
+        vm.exec();
+        vm.drain();
+    }
+}
+
+// Boot 50 NICs and run them in turn, each until it waits on input,
+// with the NAT waking them whenever the network goes idle, until it
+// sends the same y twice. A NIC with no packets reads -1, so most of
+// the time goes on polling.
+fn nics<M: Machine>(program: &str) {
+    let mut nics = (0..50).map(|addr| {
+        let mut vm = M::load(program);
+        vm.write(addr);
+        vm
+    })
+    .collect::<Vec<_>>();
+    let mut fed = vec![true; nics.len()];
+
+    let (mut nat, mut last_y) = ((0, 0), -1);
+    loop {
+        let mut packets = Vec::new();
+        for (vm, fed) in nics.iter_mut().zip(fed.iter_mut()) {
+            if !*fed {
+                vm.write(-1);
+            }
+            *fed = false;
+            vm.cont();
+            packets.extend(vm.drain());
+        }
+
+        let mut idle = true;
+        for p in packets.chunks(3) {
+            if p[0] == 255 {
+                nat = (p[1], p[2]);
+            } else {
+                idle = false;
+                let dst = p[0] as usize;
+                nics[dst].write(p[1]);
+                nics[dst].write(p[2]);
+                fed[dst] = true;
+            }
+        }
+        if idle {
+            if last_y == nat.1 {
+                return
+            }
+            last_y = nat.1;
+            nics[0].write(nat.0);
+            nics[0].write(nat.1);
+            fed[0] = true;
+        }
+    }
+}
+
+fn programs(c: &mut Criterion) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let (before, after) = (workloads::<baseline::Vm>(), workloads::<vm::Vm>());
+    for ((name, source, old), (_, _, new)) in before.iter().zip(after.iter()) {
+        let program = match source {
+            Source::File(day, file) => {
+                let path = root.join(day).join("src").join(file);
+                match fs::read_to_string(&path) {
+                    Ok(program) => program,
+                    Err(e) => {
+                        eprintln!("skipping {}: {}: {}", name, path.display(), e);
+                        continue
+                    },
+                }
+            },
+            Source::Inline(program) => program.to_string(),
+        };
+
+        let mut group = c.benchmark_group(*name);
+        group.bench_function("old", |b| b.iter(|| old(&program)));
+        group.bench_function("new", |b| b.iter(|| new(&program)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, programs);
+criterion_main!(benches);
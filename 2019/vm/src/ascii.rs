@@ -0,0 +1,90 @@
+use std::convert::TryFrom;
+
+use crate::{State, Vm, VmError};
+
+/// Text I/O for ASCII capable programs. Output values outside the
+/// ASCII range aren't text, they're kept apart as answer values.
+#[derive(Debug)]
+pub struct Ascii {
+    vm: Vm,
+    line: String,
+    values: Vec<i64>,
+}
+
+impl Ascii {
+    pub fn new(vm: Vm) -> Self {
+        Ascii { vm, line: String::new(), values: Vec::new() }
+    }
+
+    pub fn vm(&self) -> &Vm {
+        &self.vm
+    }
+
+    pub fn vm_mut(&mut self) -> &mut Vm {
+        &mut self.vm
+    }
+
+    pub fn into_inner(self) -> Vm {
+        self.vm
+    }
+
+    pub fn exec(&mut self) -> Result<State, VmError> {
+        self.vm.exec()
+    }
+
+    pub fn cont(&mut self) -> Result<State, VmError> {
+        self.vm.cont()
+    }
+
+    /// Send a line of text followed by a newline.
+    pub fn write_line(&mut self, s: &str) {
+        s.bytes().for_each(|b| self.vm.write(b));
+        self.vm.write(b'\n');
+    }
+
+    /// Next complete line of output without the newline, `None` until
+    /// the program has written one.
+    pub fn read_line(&mut self) -> Option<String> {
+        while let Some(v) = self.vm.read() {
+            match to_char(v) {
+                Some('\n') => return Some(std::mem::take(&mut self.line)),
+                Some(c) => self.line.push(c),
+                None => self.values.push(v),
+            }
+        }
+
+        None
+    }
+
+    /// All pending output text, including any partial line such as an
+    /// input prompt.
+    pub fn read_text(&mut self) -> String {
+        while let Some(v) = self.vm.read() {
+            match to_char(v) {
+                Some(c) => self.line.push(c),
+                None => self.values.push(v),
+            }
+        }
+
+        std::mem::take(&mut self.line)
+    }
+
+    /// Non-ASCII values read so far.
+    pub fn values(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.values)
+    }
+
+    /// Drain the output into its text and the last non-ASCII value,
+    /// which is how programs report a final answer.
+    pub fn output(&mut self) -> (String, Option<i64>) {
+        let text = self.read_text();
+        let value = self.values().pop();
+        (text, value)
+    }
+}
+
+fn to_char(v: i64) -> Option<char> {
+    u8::try_from(v).ok()
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
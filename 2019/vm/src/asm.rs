@@ -0,0 +1,392 @@
+//! Intcode disassembler and assembler.
+//!
+//! Operands are written as `[n]` for position mode, `n` for immediate
+//! mode and `[rb+n]` for relative mode. The assembler also accepts
+//! `name:` labels, label references (optionally `name+n`) anywhere a
+//! number is allowed, `; comments` and the `data` directive which takes
+//! numbers, labels and quoted strings.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::{getm, getop, Mode, Op};
+
+const MNEMONICS: [(Op, &str); 10] = [
+    (Op::Add,   "add"),
+    (Op::Mul,   "mul"),
+    (Op::Read,  "in"),
+    (Op::Write, "out"),
+    (Op::Jt,    "jt"),
+    (Op::Jf,    "jf"),
+    (Op::Lt,    "lt"),
+    (Op::Eq,    "eq"),
+    (Op::Arb,   "arb"),
+    (Op::Halt,  "hlt"),
+];
+
+impl Op {
+    pub fn mnemonic(&self) -> &'static str {
+        MNEMONICS.iter().find(|(op, _)| op == self).map(|(_, s)| *s).unwrap()
+    }
+
+    pub fn from_mnemonic(s: &str) -> Option<Op> {
+        MNEMONICS.iter().find(|(_, m)| *m == s).map(|(op, _)| *op)
+    }
+}
+
+/// Turn a comma separated Intcode program into annotated assembly.
+/// Words that don't decode to a valid instruction are emitted as data
+/// so the listing always assembles back to the original program.
+pub fn disassemble(program: &str) -> Result<String> {
+    let memory = program.trim()
+        .split(',')
+        .map(|s| s.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Linear sweep, one line per instruction or data word
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < memory.len() {
+        match decode(&memory, addr) {
+            Some((op, modes)) => {
+                lines.push((addr, Some((op, modes))));
+                addr += op.params() + 1;
+            },
+            None => {
+                lines.push((addr, None));
+                addr += 1;
+            }
+        }
+    }
+
+    // Label immediate jump targets that start a line
+    let starts = lines.iter().map(|(addr, _)| *addr).collect::<HashSet<_>>();
+    let targets = lines.iter()
+        .filter_map(|(addr, inst)| match inst {
+            Some((Op::Jt, modes)) | Some((Op::Jf, modes))
+                if modes[1] == Mode::Immediate => Some(memory[addr + 2]),
+            _ => None
+        })
+        .filter(|&t| t >= 0 && starts.contains(&(t as usize)))
+        .collect::<HashSet<_>>();
+
+    let mut text = String::new();
+    for (addr, inst) in lines {
+        if targets.contains(&(addr as i64)) {
+            writeln!(text, "l{}:", addr)?;
+        }
+
+        let (code, words) = match inst {
+            Some((op, modes)) => {
+                let words = &memory[addr..=addr + op.params()];
+                let args = modes.iter()
+                    .zip(&words[1..])
+                    .map(|(&m, &v)| match m {
+                        Mode::Immediate if (op == Op::Jt || op == Op::Jf)
+                            && targets.contains(&v) => format!("l{}", v),
+                        _ => operand(m, v),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                (format!("{:<4}{}", op.mnemonic(), args), words)
+            },
+            None => (format!("data {}", memory[addr]), &memory[addr..=addr])
+        };
+        let words = words.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        writeln!(text, "    {:<28}; {}: {}", code, addr, words.join(","))?;
+    }
+
+    Ok(text)
+}
+
+/// Assemble a listing into Intcode words.
+pub fn assemble(source: &str) -> Result<Vec<i64>> {
+    let lines = source.lines()
+        .enumerate()
+        .map(|(n, line)| parse_line(line).map_err(|e| anyhow!("line {}: {}", n + 1, e)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // First pass: label addresses
+    let mut labels = HashMap::new();
+    let mut addr = 0;
+    for line in &lines {
+        for label in &line.labels {
+            if labels.insert(label.clone(), addr as i64).is_some() {
+                bail!("duplicate label: {}", label)
+            }
+        }
+        addr += line.stmt.as_ref().map_or(0, Stmt::size);
+    }
+
+    // Second pass: emit words
+    let mut words = Vec::new();
+    for (n, line) in lines.iter().enumerate() {
+        let stmt = match &line.stmt {
+            Some(stmt) => stmt,
+            None => continue,
+        };
+        emit(stmt, &labels, &mut words).map_err(|e| anyhow!("line {}: {}", n + 1, e))?;
+    }
+
+    Ok(words)
+}
+
+fn decode(memory: &[i64], addr: usize) -> Option<(Op, Vec<Mode>)> {
+    let inst = memory[addr];
+    if !(0..100_000).contains(&inst) {
+        return None
+    }
+
+    let (op, mut inst) = getop(inst)?;
+    if addr + op.params() >= memory.len() {
+        return None
+    }
+
+    let mut modes = Vec::with_capacity(op.params());
+    for _ in 0..op.params() {
+        let (mode, rest) = getm(inst)?;
+        modes.push(mode);
+        inst = rest;
+    }
+
+    // Unused mode digits and immediate write targets would not survive
+    // the round trip so treat them as data.
+    let immediate_write = op.writes() && modes.last() == Some(&Mode::Immediate);
+    if inst != 0 || immediate_write {
+        None
+    } else {
+        Some((op, modes))
+    }
+}
+
+fn operand(mode: Mode, v: i64) -> String {
+    match mode {
+        Mode::Position  => format!("[{}]", v),
+        Mode::Immediate => v.to_string(),
+        Mode::Relative if v < 0 => format!("[rb{}]", v),
+        Mode::Relative  => format!("[rb+{}]", v),
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    labels: Vec<String>,
+    stmt: Option<Stmt>,
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Inst(Op, Vec<(Mode, Expr)>),
+    Data(Vec<Item>),
+}
+
+impl Stmt {
+    fn size(&self) -> usize {
+        match self {
+            Stmt::Inst(op, _) => op.params() + 1,
+            Stmt::Data(items) => items.iter()
+                .map(|item| match item {
+                    Item::Expr(_) => 1,
+                    Item::Str(s) => s.len(),
+                })
+                .sum(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Item {
+    Expr(Expr),
+    Str(Vec<i64>),
+}
+
+#[derive(Debug)]
+enum Expr {
+    Num(i64),
+    Label(String, i64),
+}
+
+impl Expr {
+    fn eval(&self, labels: &HashMap<String, i64>) -> Result<i64> {
+        match self {
+            Expr::Num(v) => Ok(*v),
+            Expr::Label(name, offset) => labels.get(name)
+                .map(|addr| addr + offset)
+                .ok_or_else(|| anyhow!("unknown label: {}", name)),
+        }
+    }
+}
+
+fn emit(stmt: &Stmt, labels: &HashMap<String, i64>, words: &mut Vec<i64>) -> Result<()> {
+    match stmt {
+        Stmt::Inst(op, args) => {
+            let inst = args.iter()
+                .rev()
+                .fold(0, |inst, (mode, _)| inst * 10 + *mode as i64);
+            words.push(inst * 100 + *op as i64);
+            for (_, expr) in args {
+                words.push(expr.eval(labels)?);
+            }
+        },
+        Stmt::Data(items) => {
+            for item in items {
+                match item {
+                    Item::Expr(expr) => words.push(expr.eval(labels)?),
+                    Item::Str(s) => words.extend(s),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_line(line: &str) -> Result<Line> {
+    let mut rest = strip_comment(line).trim();
+
+    let mut labels = Vec::new();
+    while let Some(i) = rest.find(':') {
+        let name = &rest[..i];
+        if !is_ident(name) {
+            break
+        }
+        labels.push(name.to_string());
+        rest = rest[i + 1..].trim();
+    }
+
+    if rest.is_empty() {
+        return Ok(Line { labels, stmt: None })
+    }
+
+    let (word, args) = match rest.find(char::is_whitespace) {
+        Some(i) => (&rest[..i], rest[i..].trim()),
+        None => (rest, ""),
+    };
+    let args = split_args(args);
+
+    let stmt = if word == "data" {
+        let items = args.iter()
+            .map(|arg| parse_item(arg))
+            .collect::<Result<Vec<_>>>()?;
+        Stmt::Data(items)
+    } else {
+        let op = Op::from_mnemonic(word)
+            .ok_or_else(|| anyhow!("unknown mnemonic: {}", word))?;
+        if args.len() != op.params() {
+            bail!("{} takes {} operands, found {}", word, op.params(), args.len())
+        }
+        let args = args.iter()
+            .map(|arg| parse_operand(arg))
+            .collect::<Result<Vec<_>>>()?;
+        if op.writes() && args.last().map(|(m, _)| *m) == Some(Mode::Immediate) {
+            bail!("{} cannot write to an immediate operand", word)
+        }
+        Stmt::Inst(op, args)
+    };
+
+    Ok(Line { labels, stmt: Some(stmt) })
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn split_args(args: &str) -> Vec<&str> {
+    if args.is_empty() {
+        return vec![]
+    }
+
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_operand(s: &str) -> Result<(Mode, Expr)> {
+    if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let inner = inner.trim();
+        let relative = inner.strip_prefix("rb")
+            .map(str::trim)
+            .filter(|o| o.is_empty() || o.starts_with(['+', '-']));
+        if let Some(offset) = relative {
+            let expr = if offset.is_empty() {
+                Expr::Num(0)
+            } else {
+                parse_expr(offset.trim_start_matches('+').trim())?
+            };
+            Ok((Mode::Relative, expr))
+        } else {
+            Ok((Mode::Position, parse_expr(inner)?))
+        }
+    } else {
+        Ok((Mode::Immediate, parse_expr(s)?))
+    }
+}
+
+fn parse_expr(s: &str) -> Result<Expr> {
+    if let Ok(v) = s.parse::<i64>() {
+        return Ok(Expr::Num(v))
+    }
+
+    let (name, offset) = match s.find(['+', '-']) {
+        Some(i) => {
+            let offset = s[i..].trim_start_matches('+').trim().parse::<i64>()
+                .map_err(|_| anyhow!("invalid offset: {}", s))?;
+            (s[..i].trim(), offset)
+        },
+        None => (s, 0),
+    };
+    if !is_ident(name) {
+        bail!("invalid operand: {}", s)
+    }
+
+    Ok(Expr::Label(name.to_string(), offset))
+}
+
+fn parse_item(s: &str) -> Result<Item> {
+    if let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        let mut bytes = Vec::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            let c = if c == '\\' {
+                match chars.next() {
+                    Some('n')  => '\n',
+                    Some('\\') => '\\',
+                    c => bail!("invalid escape: \\{}", c.unwrap_or(' ')),
+                }
+            } else {
+                c
+            };
+            bytes.push(c as i64);
+        }
+        Ok(Item::Str(bytes))
+    } else {
+        Ok(Item::Expr(parse_expr(s)?))
+    }
+}
@@ -0,0 +1,21 @@
+use anyhow::{bail, Context, Result};
+use std::io;
+use vm::{repl::Repl, Vm};
+
+fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (ascii, path) = match args.as_slice() {
+        [flag, path] if flag == "--ascii" => (true, path),
+        [path] => (false, path),
+        _ => bail!("usage: repl [--ascii] <program>"),
+    };
+
+    let program = std::fs::read_to_string(path)
+        .with_context(|| format!("reading {}", path))?;
+    let mut repl = Repl::new(Vm::new(&program)?);
+    if ascii {
+        repl = repl.ascii();
+    }
+
+    repl.run(io::stdin().lock(), io::stdout())
+}
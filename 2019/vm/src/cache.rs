@@ -0,0 +1,72 @@
+use crate::{getm, getop, Mode, Op};
+
+/// An instruction word split into its opcode and parameter modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Decoded {
+    pub(crate) op: Op,
+    pub(crate) modes: [Mode; 3],
+}
+
+impl Decoded {
+    /// `None` if the word isn't a valid instruction.
+    pub(crate) fn new(word: i64) -> Option<Self> {
+        let (op, inst) = getop(word)?;
+        let (m_a, inst) = getm(inst)?;
+        let (m_b, inst) = getm(inst)?;
+        let (m_c, _) = getm(inst)?;
+
+        Some(Decoded { op, modes: [m_a, m_b, m_c] })
+    }
+}
+
+/// Decoded instructions by address, so the opcode and modes of a word
+/// are only worked out once. Covers the program image, which is where
+/// code runs, and starts out with every word of it decoded; a write to
+/// a cached address evicts it.
+#[derive(Debug, Default)]
+pub(crate) struct Cache {
+    entries: Vec<Option<Decoded>>,
+}
+
+impl Cache {
+    pub(crate) fn new(image: &[i64]) -> Self {
+        Cache { entries: image.iter().map(|w| Decoded::new(*w)).collect() }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, addr: usize) -> Option<Decoded> {
+        self.entries.get(addr).copied().flatten()
+    }
+
+    pub(crate) fn insert(&mut self, addr: usize, decoded: Decoded) {
+        if let Some(entry) = self.entries.get_mut(addr) {
+            *entry = Some(decoded)
+        }
+    }
+
+    #[inline]
+    pub(crate) fn invalidate(&mut self, addr: usize) {
+        if let Some(entry) = self.entries.get_mut(addr) {
+            *entry = None
+        }
+    }
+
+    /// Decode the word now at a cached address.
+    pub(crate) fn update(&mut self, addr: usize, word: i64) {
+        if let Some(entry) = self.entries.get_mut(addr) {
+            *entry = Decoded::new(word)
+        }
+    }
+}
+
+/// `clone_from` reuses the allocation, as a vm resets its cache to the
+/// decoded program on every `exec`.
+impl Clone for Cache {
+    fn clone(&self) -> Self {
+        Cache { entries: self.entries.clone() }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.entries.clone_from(&source.entries)
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use crate::{State, Vm, VmError};
+
+/// An executed instruction as seen by the step hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// Address of the instruction that was executed.
+    pub ip: usize,
+    /// Memory cell written by the instruction, if any.
+    pub write: Option<usize>,
+}
+
+/// Why the debugger handed control back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Step,
+    Breakpoint(usize),
+    Watchpoint(usize),
+    Waiting,
+    Done,
+}
+
+type Hook = Box<dyn FnMut(&Vm, &Event)>;
+
+/// Single-step execution of a vm with breakpoints on the instruction
+/// pointer and watchpoints on memory writes.
+pub struct Debugger {
+    vm: Vm,
+    breakpoints: HashSet<usize>,
+    watchpoints: HashSet<usize>,
+    hook: Option<Hook>,
+    /// The breakpoint execution last stopped on, so continuing from it
+    /// doesn't stop there again.
+    stopped: Option<usize>,
+}
+
+impl Debugger {
+    pub fn new(vm: Vm) -> Self {
+        Debugger {
+            vm,
+            breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            hook: None,
+            stopped: None,
+        }
+    }
+
+    pub fn vm(&self) -> &Vm {
+        &self.vm
+    }
+
+    pub fn vm_mut(&mut self) -> &mut Vm {
+        &mut self.vm
+    }
+
+    pub fn into_inner(self) -> Vm {
+        self.vm
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &usize> {
+        self.breakpoints.iter()
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = &usize> {
+        self.watchpoints.iter()
+    }
+
+    /// Returns false if the breakpoint was already set.
+    pub fn set_breakpoint(&mut self, ip: usize) -> bool {
+        self.breakpoints.insert(ip)
+    }
+
+    pub fn clear_breakpoint(&mut self, ip: usize) -> bool {
+        self.breakpoints.remove(&ip)
+    }
+
+    /// Returns false if the watchpoint was already set.
+    pub fn set_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.insert(addr)
+    }
+
+    pub fn clear_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    /// Call `hook` after every instruction the debugger executes.
+    pub fn on_step<F>(&mut self, hook: F)
+        where F: FnMut(&Vm, &Event) + 'static
+    {
+        self.hook = Some(Box::new(hook))
+    }
+
+    pub fn clear_hook(&mut self) {
+        self.hook = None
+    }
+
+    /// Execute a single instruction.
+    pub fn step(&mut self) -> Result<Stop, VmError> {
+        if let Some(e) = self.vm.fault() {
+            return Err(e.clone())
+        }
+        if self.vm.is_done() {
+            return Ok(Stop::Done)
+        }
+
+        let ip = self.vm.ip;
+        self.vm.state = State::Running;
+        let write = self.vm.tick()?;
+        if let Some(hook) = self.hook.as_mut() {
+            hook(&self.vm, &Event { ip, write })
+        }
+
+        self.stopped = None;
+        let stop = match self.vm.state {
+            State::Done    => Stop::Done,
+            State::Waiting => Stop::Waiting,
+            _ => match write {
+                Some(addr) if self.watchpoints.contains(&addr) => Stop::Watchpoint(addr),
+                _ if self.breakpoints.contains(&self.vm.ip) => Stop::Breakpoint(self.vm.ip),
+                _ => Stop::Step,
+            }
+        };
+
+        if let Stop::Breakpoint(ip) = stop {
+            self.stopped = Some(ip);
+        }
+        Ok(stop)
+    }
+
+    /// Run until a breakpoint or watchpoint is hit, the vm blocks on
+    /// input or it halts.
+    pub fn cont(&mut self) -> Result<Stop, VmError> {
+        let ip = self.vm.ip;
+        if self.breakpoints.contains(&ip) && self.stopped != Some(ip) && self.vm.fault().is_none() {
+            self.stopped = Some(ip);
+            return Ok(Stop::Breakpoint(ip))
+        }
+
+        loop {
+            match self.step()? {
+                Stop::Step => continue,
+                stop => return Ok(stop),
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fmt;
+
+/// Failures loading or running an Intcode program. Execution faults
+/// carry the instruction pointer and the raw instruction at it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VmError {
+    /// A program value that isn't an integer.
+    Parse { pos: usize, text: String },
+    /// A program longer than the memory limit.
+    TooLarge { size: usize, limit: usize },
+    UnknownOpcode { ip: usize, inst: i64 },
+    InvalidMode { ip: usize, inst: i64 },
+    /// An instruction writing through an immediate mode parameter.
+    ImmediateWrite { ip: usize, inst: i64 },
+    NegativeAddress { ip: usize, inst: i64, addr: i64 },
+    /// An address at or above the memory limit.
+    OutOfRange { ip: usize, inst: i64, addr: usize },
+    /// The program needed more input than it was given.
+    InputExhausted { ip: usize, inst: i64 },
+    /// A trace sink failed to record the instruction at ip.
+    Trace { ip: usize, message: String },
+}
+
+impl VmError {
+    /// Instruction pointer of an execution fault.
+    pub fn ip(&self) -> Option<usize> {
+        use VmError::*;
+        match self {
+            Parse { .. } | TooLarge { .. } => None,
+            UnknownOpcode { ip, .. }
+            | InvalidMode { ip, .. }
+            | ImmediateWrite { ip, .. }
+            | NegativeAddress { ip, .. }
+            | OutOfRange { ip, .. }
+            | InputExhausted { ip, .. }
+            | Trace { ip, .. } => Some(*ip),
+        }
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use VmError::*;
+        match self {
+            Parse { pos, text } =>
+                write!(f, "invalid program value {:?} at position {}", text, pos),
+            TooLarge { size, limit } =>
+                write!(f, "program size {} exceeds memory limit {}", size, limit),
+            UnknownOpcode { ip, inst } =>
+                write!(f, "unknown opcode {} in {} at ip {}", inst % 100, inst, ip),
+            InvalidMode { ip, inst } =>
+                write!(f, "invalid parameter mode in {} at ip {}", inst, ip),
+            ImmediateWrite { ip, inst } =>
+                write!(f, "write in immediate mode in {} at ip {}", inst, ip),
+            NegativeAddress { ip, inst, addr } =>
+                write!(f, "negative address {} in {} at ip {}", addr, inst, ip),
+            OutOfRange { ip, inst, addr } =>
+                write!(f, "address {} out of range in {} at ip {}", addr, inst, ip),
+            InputExhausted { ip, inst } =>
+                write!(f, "input exhausted in {} at ip {}", inst, ip),
+            Trace { ip, message } =>
+                write!(f, "trace failed at ip {}: {}", ip, message),
+        }
+    }
+}
+
+impl Error for VmError {}
@@ -1,49 +1,104 @@
-use anyhow::{bail, Result};
-use std::cell::RefCell;
 use std::collections::VecDeque;
-use std::rc::Rc;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+pub mod ascii;
+pub mod asm;
+mod cache;
+pub mod debug;
+mod error;
+mod memory;
+pub mod net;
+pub mod repl;
+pub mod screen;
+pub mod trace;
+use cache::{Cache, Decoded};
+use memory::Memory;
+use trace::Trace;
+
+pub use error::VmError;
 
 #[cfg(test)]
 mod test;
 
+/// Default ceiling on addressable memory cells.
+pub const MEMORY_LIMIT: usize = 1 << 24;
+
+#[derive(Debug, Default)]
+struct Queue {
+    values: VecDeque<i64>,
+    reads: u64,
+}
+
+/// A shared FIFO of values. Pipes created with `Pipe::from` or joined
+/// with `connect` share the same queue, which may be used from several
+/// threads.
 #[derive(Debug)]
 pub struct Pipe {
-    q: Rc<RefCell<VecDeque<i64>>>
+    q: Arc<Mutex<Queue>>
 }
 
 impl Pipe {
     pub fn new() -> Self {
-        Pipe { q: Rc::new(RefCell::new(VecDeque::new())) }
+        Pipe { q: Arc::new(Mutex::new(Queue::default())) }
     }
 
     pub fn from(other: &Pipe) -> Self {
-        Pipe { q: Rc::clone(&other.q) }
+        Pipe { q: Arc::clone(&other.q) }
     }
 
     pub fn read(&mut self) -> Option<i64> {
-        (*self.q).borrow_mut().pop_front()
+        let mut q = self.lock();
+        let v = q.values.pop_front();
+        q.reads += u64::from(v.is_some());
+        v
     }
 
     pub fn write<T>(&mut self, v: T) 
         where T: std::convert::Into<i64>
     {
-        (*self.q).borrow_mut().push_back(v.into())
+        self.lock().values.push_back(v.into())
     }
 
     pub fn flush(&mut self) -> i64 {
-        let mut q = (*self.q).borrow_mut();
-        let v = q.pop_back().unwrap_or(-1i64);
-        q.clear();
+        let mut q = self.lock();
+        let v = q.values.pop_back().unwrap_or(-1i64);
+        q.values.clear();
         v
     }
 
     pub fn drain(&mut self) -> Vec<i64> {
-        let mut q = (*self.q).borrow_mut();
-        q.drain(..).collect::<Vec<i64>>()
+        self.lock().values.drain(..).collect::<Vec<i64>>()
     }
 
     pub fn connect(&mut self, other: &Pipe) {
-        self.q = Rc::clone(&other.q)
+        self.q = Arc::clone(&other.q)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().values.is_empty()
+    }
+
+    /// Copy of the queued values without consuming them.
+    pub fn contents(&self) -> Vec<i64> {
+        self.lock().values.iter().copied().collect()
+    }
+
+    /// Replace the queued values.
+    pub fn replace(&mut self, values: &[i64]) {
+        let mut q = self.lock();
+        q.values.clear();
+        q.values.extend(values)
+    }
+
+    /// Number of values read from the queue so far.
+    pub(crate) fn reads(&self) -> u64 {
+        self.lock().reads
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        // A panic while holding the lock can't leave the queue invalid
+        self.q.lock().unwrap_or_else(PoisonError::into_inner)
     }
 }
 
@@ -57,6 +112,21 @@ pub enum State {
     Running,
     Waiting,
   Done,
+    /// Stopped by an error, see `Vm::fault`.
+    Faulted,
+}
+
+/// Saved machine state, see `Vm::snapshot`.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    ip: usize,
+    base: i64,
+    state: State,
+    fault: Option<VmError>,
+    memory: Memory,
+    cache: Cache,
+    input: Vec<i64>,
+    output: Vec<i64>,
 }
 
 #[derive(Debug)]
@@ -64,36 +134,56 @@ pub struct Vm {
     ip: usize,
     base: i64,
     state: State,
+    fault: Option<VmError>,
     input: Pipe,
     output: Pipe,
-    memory: [i64; 8192],
-    backup: [i64; 8192],
+    memory: Memory,
+    backup: Memory,
+    cache: Cache,
+    /// The decoded program, which `cache` starts from.
+    decoded: Cache,
 }
 
 impl Vm {
-    pub fn new(program: &str) -> Result<Self> {
-        let mut vm = Vm { 
-            memory: [0; 8192],
-            backup: [0; 8192],
+    pub fn new(program: &str) -> Result<Self, VmError> {
+        Vm::with_limit(program, MEMORY_LIMIT)
+    }
+
+    /// Load a program allowing at most `limit` memory cells.
+    pub fn with_limit(program: &str, limit: usize) -> Result<Self, VmError> {
+        // Copy instructions into backup memory
+        let image = program.trim()
+            .split(',')
+            .enumerate()
+            .map(|(pos, s)| s.parse::<i64>()
+                .map_err(|_| VmError::Parse { pos, text: s.to_string() }))
+            .collect::<Result<Vec<_>, _>>()?;
+        if image.len() > limit {
+            return Err(VmError::TooLarge { size: image.len(), limit })
+        }
+
+        let decoded = Cache::new(&image);
+        let backup = Memory::new(image, limit);
+        let vm = Vm { 
+            memory: backup.clone(),
+            backup,
+            cache: decoded.clone(),
+            decoded,
             ip: 0,
             base: 0,
             state: State::Ready,
+            fault: None,
             input: Pipe::new(),
             output: Pipe::new()
         };
 
-        // Copy instructions into backup memory
-        program.split(',')
-            .enumerate()
-            .try_for_each(|(i, s)| -> Result<()> {
-                let v = s.parse::<i64>()?;
-                vm.backup[i] = v;
-                Ok(())
-            })?;
-
         Ok(vm)
     }
 
+    pub fn limit(&self) -> usize {
+        self.backup.limit()
+    }
+
     pub fn pipes(&self) -> (Pipe, Pipe) {
         (Pipe::from(&self.input), Pipe::from(&self.output))
     }
@@ -125,134 +215,344 @@ impl Vm {
         other.input.connect(&self.output)
     }
 
-    pub fn exec(&mut self) -> Result<State> {
-        // Restore the program to original state
-        self.memory = self.backup;
-        self.ip = 0;
-        self.base = 0;
+    pub fn exec(&mut self) -> Result<State, VmError> {
+        self.reset();
         self.run()?;
 
         Ok(self.state)
     }
 
-    pub fn cont(&mut self) -> Result<State> {
+    pub fn cont(&mut self) -> Result<State, VmError> {
         if self.is_running() {
             self.run()?;
         }
 
+        match &self.fault {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.state),
+        }
+    }
+
+    /// Continue running a program that must halt with the input it has
+    /// been given.
+    pub fn finish(&mut self) -> Result<(), VmError> {
+        if self.state == State::Ready {
+            self.run()?;
+        } else {
+            self.cont()?;
+        }
+
+        match self.state {
+            State::Waiting => Err(VmError::InputExhausted { ip: self.ip, inst: self.inst() }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The error that stopped a faulted vm.
+    pub fn fault(&self) -> Option<&VmError> {
+        self.fault.as_ref()
+    }
+
+    /// Like `exec` but passes every executed instruction to `tracer`.
+    pub fn exec_traced(&mut self, tracer: &mut dyn Trace) -> Result<State, VmError> {
+        self.reset();
+        self.run_traced(tracer)
+    }
+
+    /// Like `cont` but passes every executed instruction to `tracer`.
+    pub fn cont_traced(&mut self, tracer: &mut dyn Trace) -> Result<State, VmError> {
+        if self.is_running() {
+            self.run_traced(tracer)?;
+        }
+
+        match &self.fault {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.state),
+        }
+    }
+
+    /// Save the registers, memory and queued pipe contents.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            base: self.base,
+            state: self.state,
+            fault: self.fault.clone(),
+            memory: self.memory.clone(),
+            cache: self.cache.clone(),
+            input: self.input.contents(),
+            output: self.output.contents(),
+        }
+    }
+
+    /// Return to a saved state. Pipes stay connected, only their
+    /// contents are replaced.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.ip = snapshot.ip;
+        self.base = snapshot.base;
+        self.state = snapshot.state;
+        self.fault = snapshot.fault.clone();
+        self.memory.clone_from(&snapshot.memory);
+        self.cache.clone_from(&snapshot.cache);
+        self.input.replace(&snapshot.input);
+        self.output.replace(&snapshot.output);
+    }
+
+    /// Execute a single instruction.
+    pub fn step(&mut self) -> Result<State, VmError> {
+        if let Some(e) = &self.fault {
+            return Err(e.clone())
+        }
+        if self.state != State::Done {
+            self.state = State::Running;
+            self.tick()?;
+        }
+
         Ok(self.state)
     }
 
-    pub fn set_addr(&mut self, addr: usize, v: i64) {
-        self.memory[addr] = v
+    /// Restore the program to its original state without running it.
+    pub fn reset(&mut self) {
+        self.memory.clone_from(&self.backup);
+        self.cache.clone_from(&self.decoded);
+        self.ip = 0;
+        self.base = 0;
+        self.state = State::Ready;
+        self.fault = None;
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn base(&self) -> i64 {
+        self.base
+    }
+
+    pub fn state(&self) -> State {
+        self.state
     }
 
-    pub fn edit_program(&mut self, addr: usize, v: i64) {
-        self.backup[addr] = v
+    /// Read a memory cell, `None` if the address is out of range.
+    pub fn peek(&self, addr: usize) -> Option<i64> {
+        self.memory.get(addr)
     }
 
-    fn run(&mut self) -> Result<State> {
+    pub fn set_addr(&mut self, addr: usize, v: i64) -> Result<(), VmError> {
+        self.cache.invalidate(addr);
+        self.memory.set(addr, v)
+            .ok_or_else(|| VmError::OutOfRange { ip: self.ip, inst: self.inst(), addr })
+    }
+
+    pub fn edit_program(&mut self, addr: usize, v: i64) -> Result<(), VmError> {
+        self.decoded.update(addr, v);
+        self.backup.set(addr, v)
+            .ok_or_else(|| VmError::OutOfRange { ip: self.ip, inst: self.inst(), addr })
+    }
+
+    fn run(&mut self) -> Result<State, VmError> {
         self.state = State::Running;
         while self.state == State::Running {
-            let inst = self.memory[self.ip];
-            let (opc, inst) = getop(inst)?;
-            let (m_a, inst) = getm(inst)?;
-            let (m_b, inst) = getm(inst)?;
-            let (m_c, _) = getm(inst)?;
-
-            self.ip = match opc  {
-                Op::Add => {
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    self.setv(m_c, self.ip + 3, a+b)?;
-                    self.ip + 4
-                },
-                Op::Mul => {
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    self.setv(m_c, self.ip + 3, a*b)?;
-                    self.ip + 4
-                },
-                Op::Read => {
-                    // read-from-input
-                    if let Some(v) = self.input.read() {
-                        self.setv(m_a, self.ip + 1, v)?;
-                        self.ip + 2
-                    } else {
-                        self.state = State::Waiting;
-                        self.ip
-                    }
-                },
-                Op::Jt => {
-                    // jump-if-true
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    if a != 0 { b as usize } else { self.ip + 3 }
-                },
-                Op::Jf => {
-                    // jump-if-false
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    if a == 0 { b as usize } else { self.ip + 3 }
-                },
-                Op::Lt => {
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    let v = i64::from(a < b);
-                    self.setv(m_c, self.ip + 3, v)?;
-                    self.ip + 4
-                },
-                Op::Eq => {
-                    let a = self.getv(m_a, self.ip + 1);
-                    let b = self.getv(m_b, self.ip + 2);
-                    let v = i64::from(a == b);
-                    self.setv(m_c, self.ip + 3, v)?;
-                    self.ip + 4
-                },
-                Op::Arb => {
-                    // adjust-relative-base
-                    let a = self.getv(m_a, self.ip + 1);
-                    self.base += a;
-                    self.ip + 2
-                },
-                Op::Write => {
-                    // write-to-output
-                    let a = self.getv(m_a, self.ip + 1);
-                    self.output.write(a);
+            self.tick()?;
+        }
+
+        Ok(self.state)
+    }
+
+    /// Execute the instruction at ip, returning the address written to
+    /// memory, if any. An error leaves the vm faulted at that ip.
+    pub(crate) fn tick(&mut self) -> Result<Option<usize>, VmError> {
+        self.execute().inspect_err(|e| {
+            self.state = State::Faulted;
+            self.fault = Some(e.clone());
+        })
+    }
+
+    fn execute(&mut self) -> Result<Option<usize>, VmError> {
+        let Decoded { op: opc, modes: [m_a, m_b, m_c] } = match self.cache.get(self.ip) {
+            Some(decoded) => decoded,
+            None => {
+                let decoded = self.decode_inst()?;
+                self.cache.insert(self.ip, decoded);
+                decoded
+            },
+        };
+
+        let mut written = None;
+        self.ip = match opc  {
+            Op::Add => {
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                written = Some(self.setv(m_c, self.ip + 3, a+b)?);
+                self.ip + 4
+            },
+            Op::Mul => {
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                written = Some(self.setv(m_c, self.ip + 3, a*b)?);
+                self.ip + 4
+            },
+            Op::Read => {
+                // read-from-input
+                if let Some(v) = self.input.read() {
+                    written = Some(self.setv(m_a, self.ip + 1, v)?);
                     self.ip + 2
-                },
-                Op::Halt => {
-                    self.state = State::Done;
-                    0
+                } else {
+                    self.state = State::Waiting;
+                    self.ip
                 }
+            },
+            Op::Jt => {
+                // jump-if-true
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                if a != 0 { self.address(b)? } else { self.ip + 3 }
+            },
+            Op::Jf => {
+                // jump-if-false
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                if a == 0 { self.address(b)? } else { self.ip + 3 }
+            },
+            Op::Lt => {
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                let v = i64::from(a < b);
+                written = Some(self.setv(m_c, self.ip + 3, v)?);
+                self.ip + 4
+            },
+            Op::Eq => {
+                let a = self.getv(m_a, self.ip + 1)?;
+                let b = self.getv(m_b, self.ip + 2)?;
+                let v = i64::from(a == b);
+                written = Some(self.setv(m_c, self.ip + 3, v)?);
+                self.ip + 4
+            },
+            Op::Arb => {
+                // adjust-relative-base
+                let a = self.getv(m_a, self.ip + 1)?;
+                self.base += a;
+                self.ip + 2
+            },
+            Op::Write => {
+                // write-to-output
+                let a = self.getv(m_a, self.ip + 1)?;
+                self.output.write(a);
+                self.ip + 2
+            },
+            Op::Halt => {
+                self.state = State::Done;
+                0
             }
-        }
+        };
 
-        Ok(self.state)
+        Ok(written)
     }
 
-    fn getv(&self, mode: Mode, pos: usize) -> i64 {
-        let v = self.memory[pos];
+    fn decode_inst(&self) -> Result<Decoded, VmError> {
+        let raw = self.load(self.ip as i64)?;
+        let ip = self.ip;
+        Decoded::new(raw).ok_or(match getop(raw) {
+            None => VmError::UnknownOpcode { ip, inst: raw },
+            Some(_) => VmError::InvalidMode { ip, inst: raw },
+        })
+    }
+
+    // The accessors are forced inline: left as calls, with the error
+    // paths they carry, they cost more per instruction than decoding.
+    #[inline(always)]
+    fn getv(&self, mode: Mode, pos: usize) -> Result<i64, VmError> {
+        let v = self.param(pos)?;
         match mode {
-            Mode::Position  => self.memory[v as usize],
-            Mode::Relative  => self.memory[(v + self.base) as usize],
-            Mode::Immediate => v,
+            Mode::Position  => self.load(v),
+            Mode::Relative  => self.load(v + self.base),
+            Mode::Immediate => Ok(v),
         }
     }
 
-    fn setv(&mut self, mode: Mode, pos: usize, val: i64) -> Result<()> {
-        let v = self.memory[pos];
+    #[inline(always)]
+    fn setv(&mut self, mode: Mode, pos: usize, val: i64) -> Result<usize, VmError> {
+        let v = self.param(pos)?;
         match mode {
-            Mode::Position  => self.memory[v as usize] = val,
-            Mode::Relative  => self.memory[(v + self.base) as usize] = val,
-            Mode::Immediate => bail!("Immediate mode not allowed for setting values"),
-        };
-        Ok(())
+            Mode::Position  => self.store(v, val),
+            Mode::Relative  => self.store(v + self.base, val),
+            Mode::Immediate => Err(VmError::ImmediateWrite { ip: self.ip, inst: self.inst() }),
+        }
+    }
+
+    /// The raw parameter word at `pos`.
+    #[inline(always)]
+    fn param(&self, pos: usize) -> Result<i64, VmError> {
+        match self.memory.get(pos) {
+            Some(v) => Ok(v),
+            None => Err(self.bad_address(pos as i64)),
+        }
+    }
+
+    #[inline(always)]
+    fn load(&self, addr: i64) -> Result<i64, VmError> {
+        match usize::try_from(addr).ok().and_then(|a| self.memory.get(a)) {
+            Some(v) => Ok(v),
+            None => Err(self.bad_address(addr)),
+        }
+    }
+
+    #[inline(always)]
+    fn store(&mut self, addr: i64, val: i64) -> Result<usize, VmError> {
+        let a = usize::try_from(addr).map_err(|_| self.bad_address(addr))?;
+        self.cache.invalidate(a);
+        match self.memory.set(a, val) {
+            Some(()) => Ok(a),
+            None => Err(self.bad_address(addr)),
+        }
+    }
+
+    fn address(&self, addr: i64) -> Result<usize, VmError> {
+        usize::try_from(addr).map_err(|_| self.bad_address(addr))
+    }
+
+    /// The fault for an address that is negative or out of range.
+    #[cold]
+    fn bad_address(&self, addr: i64) -> VmError {
+        let (ip, inst) = (self.ip, self.inst());
+        match usize::try_from(addr) {
+            Ok(addr) => VmError::OutOfRange { ip, inst, addr },
+            Err(_) => VmError::NegativeAddress { ip, inst, addr },
+        }
+    }
+
+    /// Raw instruction at ip, for error reporting.
+    fn inst(&self) -> i64 {
+        self.memory.get(self.ip).unwrap_or_default()
     }
 }
 
-#[derive(Debug)]
-enum Op {
+/// An independent copy: memory and registers are duplicated and the
+/// clone gets its own pipes holding the same queued values.
+impl Clone for Vm {
+    fn clone(&self) -> Self {
+        let mut input = Pipe::new();
+        let mut output = Pipe::new();
+        input.replace(&self.input.contents());
+        output.replace(&self.output.contents());
+
+        Vm {
+            ip: self.ip,
+            base: self.base,
+            state: self.state,
+            fault: self.fault.clone(),
+            input,
+            output,
+            memory: self.memory.clone(),
+            backup: self.backup.clone(),
+            cache: self.cache.clone(),
+            decoded: self.decoded.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
     Add = 1,
     Mul = 2,
     Read = 3,
@@ -265,14 +565,33 @@ enum Op {
     Halt = 99,
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Position = 0,
     Immediate = 1,
     Relative = 2,
 }
 
-fn getop(inst: i64) -> Result<(Op, i64)> {
+impl Op {
+    /// Number of parameters following the opcode.
+    pub fn params(&self) -> usize {
+        use Op::*;
+        match self {
+            Add | Mul | Lt | Eq => 3,
+            Jt | Jf => 2,
+            Read | Write | Arb => 1,
+            Halt => 0,
+        }
+    }
+
+    /// Whether the last parameter is a write target.
+    pub fn writes(&self) -> bool {
+        use Op::*;
+        matches!(self, Add | Mul | Lt | Eq | Read)
+    }
+}
+
+fn getop(inst: i64) -> Option<(Op, i64)> {
     let op = match inst % 100 {
         1 => Op::Add,
         2 => Op::Mul,
@@ -284,19 +603,19 @@ fn getop(inst: i64) -> Result<(Op, i64)> {
         8 => Op::Eq,
         9 => Op::Arb,
         99 => Op::Halt,
-        _  => bail!("Unknown opcode encountered: {}:{}", inst, inst % 100), 
+        _  => return None,
     };
 
-    Ok((op, inst / 100))
+    Some((op, inst / 100))
 }
 
-fn getm(inst: i64) -> Result<(Mode, i64)> {
+fn getm(inst: i64) -> Option<(Mode, i64)> {
     let mode = match inst % 10 {
         0 => Mode::Position,
         1 => Mode::Immediate,
         2 => Mode::Relative,
-        _ => bail!("Invalid mode encountered: {}:{}", inst, inst % 10),
+        _ => return None,
     };
 
-    Ok((mode, inst / 10))
+    Some((mode, inst / 10))
 }
@@ -0,0 +1,105 @@
+const PAGE_SIZE: usize = 1024;
+
+/// Intcode memory: the program image is held densely and grows a page
+/// at a time as the program writes just past its end, which is where
+/// stacks live. Anything written further up lives in zero-filled pages
+/// allocated on demand, indexed by page number.
+/// Addresses at or above the limit are out of range.
+#[derive(Debug)]
+pub(crate) struct Memory {
+    dense: Vec<i64>,
+    pages: Vec<Option<Box<[i64; PAGE_SIZE]>>>,
+    limit: usize,
+}
+
+impl Memory {
+    pub(crate) fn new(image: Vec<i64>, limit: usize) -> Self {
+        Memory { dense: image, pages: Vec::new(), limit }
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, addr: usize) -> Option<i64> {
+        match self.dense.get(addr) {
+            Some(v) => Some(*v),
+            None => self.get_sparse(addr),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set(&mut self, addr: usize, v: i64) -> Option<()> {
+        match self.dense.get_mut(addr) {
+            Some(cell) => *cell = v,
+            None => self.set_sparse(addr, v)?,
+        }
+
+        Some(())
+    }
+
+    #[cold]
+    fn get_sparse(&self, addr: usize) -> Option<i64> {
+        if addr >= self.limit {
+            return None
+        }
+
+        let v = self.pages.get(addr / PAGE_SIZE)
+            .and_then(Option::as_ref)
+            .map_or(0, |page| page[addr % PAGE_SIZE]);
+        Some(v)
+    }
+
+    #[cold]
+    fn set_sparse(&mut self, addr: usize, v: i64) -> Option<()> {
+        if addr >= self.limit {
+            return None
+        }
+
+        if addr < self.dense.len() + PAGE_SIZE {
+            self.grow(addr);
+            self.dense[addr] = v;
+        } else {
+            let n = addr / PAGE_SIZE;
+            if n >= self.pages.len() {
+                self.pages.resize_with(n + 1, || None);
+            }
+            let page = self.pages[n].get_or_insert_with(|| Box::new([0; PAGE_SIZE]));
+            page[addr % PAGE_SIZE] = v;
+        }
+
+        Some(())
+    }
+
+    /// Extend the dense cells to the page boundary above `addr`, taking
+    /// over any pages they now cover.
+    fn grow(&mut self, addr: usize) {
+        let end = ((addr / PAGE_SIZE + 1) * PAGE_SIZE).min(self.limit);
+        let start = self.dense.len();
+        self.dense.resize(end, 0);
+
+        let covered = end.div_ceil(PAGE_SIZE).min(self.pages.len());
+        for (n, page) in self.pages[..covered].iter_mut().enumerate() {
+            if let Some(page) = page.take() {
+                let from = (n * PAGE_SIZE).max(start);
+                let to = ((n + 1) * PAGE_SIZE).min(end);
+                self.dense[from..to].copy_from_slice(&page[from - n * PAGE_SIZE..to - n * PAGE_SIZE]);
+            }
+        }
+    }
+}
+
+/// Clones copy the cells in use. `clone_from` reuses the allocation,
+/// which keeps resetting a vm to its program cheap.
+impl Clone for Memory {
+    fn clone(&self) -> Self {
+        Memory { dense: self.dense.clone(), pages: self.pages.clone(), limit: self.limit }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.dense.clone_from(&source.dense);
+        self.pages.clone_from(&source.pages);
+        self.limit = source.limit;
+    }
+}
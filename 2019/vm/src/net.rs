@@ -0,0 +1,136 @@
+//! Scheduling a graph of vms connected by pipes.
+//!
+//! The network runs in rounds: every vm that can make progress is run
+//! until it blocks on input or halts. Rounds repeat until one passes
+//! with no vm consuming input, at which point the network is idle (or
+//! done, if every vm has halted).
+
+use std::thread;
+
+use crate::{Pipe, State, Vm, VmError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run the vms one after another on the calling thread.
+    Cooperative,
+    /// Run the vms of each round on up to this many threads.
+    Threaded(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Every vm has halted.
+    Done,
+    /// Every running vm is waiting on an empty input.
+    Idle,
+}
+
+#[derive(Debug, Default)]
+pub struct Network {
+    vms: Vec<Vm>,
+    idle_input: Option<i64>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a vm, returning its id.
+    pub fn add(&mut self, vm: Vm) -> usize {
+        self.vms.push(vm);
+        self.vms.len() - 1
+    }
+
+    /// Feed the output of `from` into the input of `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        let pipe = Pipe::from(&self.vms[from].output);
+        self.vms[to].input.connect(&pipe)
+    }
+
+    /// Value given to a vm reading from an empty input, instead of
+    /// blocking it. Day 23 NICs expect -1.
+    pub fn set_idle_input(&mut self, v: Option<i64>) {
+        self.idle_input = v
+    }
+
+    pub fn len(&self) -> usize {
+        self.vms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vms.is_empty()
+    }
+
+    pub fn vm(&self, id: usize) -> &Vm {
+        &self.vms[id]
+    }
+
+    pub fn vm_mut(&mut self, id: usize) -> &mut Vm {
+        &mut self.vms[id]
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Vm> {
+        self.vms.iter_mut()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.vms.iter().all(Vm::is_done)
+    }
+
+    /// Run rounds until the network is idle or done.
+    pub fn run(&mut self, schedule: Schedule) -> Result<Status, VmError> {
+        while self.round(schedule)? {}
+
+        let status = if self.is_done() { Status::Done } else { Status::Idle };
+        Ok(status)
+    }
+
+    /// Run every vm that can make progress, returning whether any of
+    /// them consumed input or started.
+    fn round(&mut self, schedule: Schedule) -> Result<bool, VmError> {
+        let reads = self.reads();
+        let mut fed = 0;
+        let mut started = false;
+        let mut runnable = Vec::new();
+        for vm in self.vms.iter_mut() {
+            match vm.state {
+                State::Done | State::Faulted => continue,
+                State::Ready | State::Running => started = true,
+                State::Waiting if vm.input.is_empty() => match self.idle_input {
+                    Some(v) => {
+                        vm.input.write(v);
+                        fed += 1;
+                    },
+                    None => continue,
+                },
+                State::Waiting => {},
+            }
+            runnable.push(vm);
+        }
+
+        match schedule {
+            Schedule::Threaded(n) if n > 1 && runnable.len() > 1 => {
+                let size = runnable.len().div_ceil(n);
+                thread::scope(|s| {
+                    let workers = runnable.chunks_mut(size)
+                        .map(|chunk| s.spawn(move || {
+                            chunk.iter_mut().try_for_each(|vm| vm.run().map(|_| ()))
+                        }))
+                        .collect::<Vec<_>>();
+                    workers.into_iter()
+                        .try_for_each(|w| w.join().expect("vm thread panicked"))
+                })?;
+            },
+            _ => runnable.into_iter().try_for_each(|vm| vm.run().map(|_| ()))?,
+        }
+
+        let consumed = self.reads() - reads > fed;
+        let pending = self.vms.iter().any(|vm| !vm.is_done() && !vm.input.is_empty());
+        Ok(started || consumed || pending)
+    }
+
+    fn reads(&self) -> u64 {
+        self.vms.iter().map(|vm| vm.input.reads()).sum()
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{BufRead, Write};
+
+use crate::debug::{Debugger, Stop};
+use crate::Vm;
+
+const HELP: &str = "\
+.s [n]       step n instructions (default 1)
+.c           continue until a break, watch, input or halt
+.b [addr]    toggle a breakpoint, list them without an address
+.w [addr]    toggle a watchpoint, list them without an address
+.r           show ip, relative base and state
+.x addr [n]  show n memory cells starting at addr
+.reset       restart the program
+.q           quit
+anything else is sent to the program as input";
+
+/// Interactive front end over a `Debugger`. Lines starting with `.` are
+/// debugger commands, anything else is program input: text in ASCII
+/// mode, comma or space separated numbers otherwise.
+pub struct Repl {
+    dbg: Debugger,
+    ascii: bool,
+    aliases: HashMap<String, String>,
+}
+
+impl Repl {
+    pub fn new(vm: Vm) -> Self {
+        Repl { dbg: Debugger::new(vm), ascii: false, aliases: HashMap::new() }
+    }
+
+    pub fn ascii(mut self) -> Self {
+        self.ascii = true;
+        self
+    }
+
+    /// Replace an input line matching `from` with `to`.
+    pub fn alias(mut self, from: &str, to: &str) -> Self {
+        self.aliases.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    pub fn debugger(&mut self) -> &mut Debugger {
+        &mut self.dbg
+    }
+
+    pub fn run<R, W>(&mut self, input: R, mut out: W) -> Result<()>
+        where R: BufRead, W: Write
+    {
+        if !self.dbg.vm().is_running() {
+            self.dbg.vm_mut().reset();
+            self.resume(&mut out)?;
+        }
+
+        for line in input.lines() {
+            let line = line?;
+            let line = self.aliases.get(line.trim()).cloned().unwrap_or(line);
+            if let Some(cmd) = line.strip_prefix('.') {
+                let mut args = cmd.split_whitespace();
+                match args.next() {
+                    Some("q") => break,
+                    Some(cmd) => {
+                        let args = args.map(|s| s.parse::<usize>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| anyhow!("invalid argument"));
+                        match args.and_then(|args| self.command(cmd, &args, &mut out)) {
+                            Ok(()) => {},
+                            Err(e) => writeln!(out, "error: {}", e)?,
+                        }
+                    },
+                    None => writeln!(out, "{}", HELP)?,
+                }
+            } else {
+                if self.ascii {
+                    line.bytes().for_each(|b| self.dbg.vm_mut().write(b));
+                    self.dbg.vm_mut().write(b'\n');
+                } else {
+                    let values = line.split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.parse::<i64>())
+                        .collect::<Result<Vec<_>, _>>();
+                    match values {
+                        Ok(values) => values.into_iter().for_each(|v| self.dbg.vm_mut().write(v)),
+                        Err(e) => {
+                            writeln!(out, "error: {}", e)?;
+                            continue
+                        }
+                    }
+                }
+                self.resume(&mut out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn command<W: Write>(&mut self, cmd: &str, args: &[usize], out: &mut W) -> Result<()> {
+        match (cmd, args) {
+            ("s", _) => {
+                for _ in 0..args.first().copied().unwrap_or(1) {
+                    match self.dbg.step() {
+                        Ok(Stop::Step) => {},
+                        Ok(stop) => {
+                            self.report(stop, out)?;
+                            break
+                        },
+                        Err(e) => {
+                            writeln!(out, "fault: {}", e)?;
+                            break
+                        },
+                    }
+                }
+                self.registers(out)?;
+                self.output(out)?;
+            },
+            ("c", []) => self.resume(out)?,
+            ("b", []) => {
+                let mut addrs = self.dbg.breakpoints().copied().collect::<Vec<_>>();
+                addrs.sort_unstable();
+                writeln!(out, "breakpoints: {:?}", addrs)?;
+            },
+            ("b", [addr]) => {
+                if !self.dbg.set_breakpoint(*addr) {
+                    self.dbg.clear_breakpoint(*addr);
+                }
+            },
+            ("w", []) => {
+                let mut addrs = self.dbg.watchpoints().copied().collect::<Vec<_>>();
+                addrs.sort_unstable();
+                writeln!(out, "watchpoints: {:?}", addrs)?;
+            },
+            ("w", [addr]) => {
+                if !self.dbg.set_watchpoint(*addr) {
+                    self.dbg.clear_watchpoint(*addr);
+                }
+            },
+            ("r", []) => self.registers(out)?,
+            ("x", [addr]) | ("x", [addr, _]) => {
+                let n = args.get(1).copied().unwrap_or(1);
+                let cells = (*addr..addr + n)
+                    .map(|a| self.dbg.vm().peek(a).map_or("-".to_string(), |v| v.to_string()))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}: {}", addr, cells.join(","))?;
+            },
+            ("reset", []) => {
+                self.dbg.vm_mut().reset();
+                self.resume(out)?;
+            },
+            _ => writeln!(out, "{}", HELP)?,
+        }
+
+        Ok(())
+    }
+
+    /// Continue the program. A fault is reported rather than returned
+    /// so the session can carry on inspecting or reset.
+    fn resume<W: Write>(&mut self, out: &mut W) -> Result<()> {
+        let stop = self.dbg.cont();
+        self.output(out)?;
+        match stop {
+            Ok(stop) => self.report(stop, out),
+            Err(e) => Ok(writeln!(out, "fault: {}", e)?),
+        }
+    }
+
+    fn report<W: Write>(&self, stop: Stop, out: &mut W) -> Result<()> {
+        match stop {
+            Stop::Breakpoint(ip)  => writeln!(out, "breakpoint at {}", ip)?,
+            Stop::Watchpoint(addr) => {
+                let v = self.dbg.vm().peek(addr).unwrap_or_default();
+                writeln!(out, "watchpoint {} = {} at {}", addr, v, self.dbg.vm().ip())?
+            },
+            Stop::Done => writeln!(out, "halted")?,
+            Stop::Step | Stop::Waiting => {},
+        }
+
+        Ok(())
+    }
+
+    fn registers<W: Write>(&self, out: &mut W) -> Result<()> {
+        let vm = self.dbg.vm();
+        writeln!(out, "ip: {} base: {} state: {:?}", vm.ip(), vm.base(), vm.state())?;
+        Ok(())
+    }
+
+    fn output<W: Write>(&mut self, out: &mut W) -> Result<()> {
+        let values = self.dbg.vm_mut().drain();
+        if self.ascii {
+            for v in values {
+                match u8::try_from(v) {
+                    Ok(b) if b.is_ascii() => write!(out, "{}", b as char)?,
+                    _ => writeln!(out, "{}", v)?,
+                }
+            }
+        } else if !values.is_empty() {
+            let values = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            writeln!(out, "{}", values.join(","))?;
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+}
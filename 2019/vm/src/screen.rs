@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A sparse 2D framebuffer built from program output, either
+/// `x, y, value` triples or rows of ASCII text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Screen {
+    pixels: HashMap<(i64, i64), i64>,
+    bounds: Option<((i64, i64), (i64, i64))>,
+    score: Option<i64>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Screen holding the characters of `text`, one row per line.
+    pub fn from_ascii(text: &str) -> Self {
+        let mut screen = Screen::new();
+        screen.draw_ascii(text);
+        screen
+    }
+
+    /// Apply `x, y, value` triples. The position `-1, 0` isn't a pixel,
+    /// it's the segment display showing the score.
+    pub fn draw(&mut self, values: &[i64]) {
+        for triple in values.chunks_exact(3) {
+            match *triple {
+                [-1, 0, score] => self.score = Some(score),
+                [x, y, v] => self.set(x, y, v),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Set pixels from the characters of `text`, starting at the top
+    /// left corner.
+    pub fn draw_ascii(&mut self, text: &str) {
+        for (y, row) in text.lines().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                self.set(x as i64, y as i64, c as i64)
+            }
+        }
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, v: i64) {
+        self.pixels.insert((x, y), v);
+        self.bounds = Some(match self.bounds {
+            None => ((x, y), (x, y)),
+            Some((tl, br)) => ((tl.0.min(x), tl.1.min(y)), (br.0.max(x), br.1.max(y))),
+        });
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Option<i64> {
+        self.pixels.get(&(x, y)).copied()
+    }
+
+    /// Last value shown on the segment display.
+    pub fn score(&self) -> Option<i64> {
+        self.score
+    }
+
+    /// Top left and bottom right corners of everything drawn.
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        self.bounds
+    }
+
+    pub fn width(&self) -> usize {
+        self.bounds.map_or(0, |(tl, br)| (br.0 - tl.0 + 1) as usize)
+    }
+
+    pub fn height(&self) -> usize {
+        self.bounds.map_or(0, |(tl, br)| (br.1 - tl.1 + 1) as usize)
+    }
+
+    /// Positions of every pixel with the value `v`.
+    pub fn find(&self, v: i64) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.pixels.iter()
+            .filter(move |(_, &p)| p == v)
+            .map(|(&pos, _)| pos)
+    }
+
+    pub fn count(&self, v: i64) -> usize {
+        self.find(v).count()
+    }
+
+    /// Draw the bounding box a row at a time, mapping each pixel (or
+    /// `None` where nothing was drawn) to a character.
+    pub fn render<F>(&self, f: F) -> String
+        where F: Fn(Option<i64>) -> char
+    {
+        let ((x0, y0), (x1, y1)) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+
+        (y0..=y1)
+            .map(|y| (x0..=x1).map(|x| f(self.get(x, y))).collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+/// Renders pixels as ASCII characters, blank where nothing was drawn.
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.render(|v| {
+            v.and_then(|v| u8::try_from(v).ok())
+                .filter(u8::is_ascii)
+                .map_or(' ', char::from)
+        });
+        write!(f, "{}", text)
+    }
+}
@@ -225,3 +225,420 @@ fn large_output() {
   assert_eq!(vm.exec().unwrap(), State::Done);
   assert_eq!(stdout.flush(), 1219070632396864);
 }
+
+#[test]
+fn high_memory() {
+  let program = "1101,7,35,100000,4,100000,99";
+  let mut vm = Vm::new(program).unwrap();
+  let (_, mut stdout) = vm.pipes();
+
+  assert_eq!(vm.exec().unwrap(), State::Done);
+  assert_eq!(stdout.flush(), 42);
+}
+
+#[test]
+fn growing_memory() {
+  // Writes past the program grow it, taking over the page at 1100
+  let program = "1101,1,2,1100,1101,3,4,20,1101,5,6,1030,4,1100,4,20,4,1030,99";
+  let mut vm = Vm::with_limit(program, 1500).unwrap();
+
+  assert_eq!(vm.exec().unwrap(), State::Done);
+  assert_eq!(vm.drain(), [3, 7, 11]);
+  assert_eq!(vm.peek(1499), Some(0));
+  assert_eq!(vm.peek(1500), None);
+
+  vm.reset();
+  assert_eq!(vm.peek(1100), Some(0));
+}
+
+#[test]
+fn memory_limit() {
+  let program = "1101,7,35,100,99";
+  let mut vm = Vm::with_limit(program, 64).unwrap();
+
+  let err = vm.exec().unwrap_err();
+  assert!(err.to_string().contains("ip 0"));
+  assert!(Vm::with_limit(program, 4).is_err());
+}
+
+#[test]
+fn negative_address() {
+  let program = "4,-1,99";
+  let mut vm = Vm::new(program).unwrap();
+
+  assert!(vm.exec().is_err());
+}
+
+#[test]
+fn disassemble() {
+  let program = "1002,4,3,4,33,109,-2,1105,1,7";
+  let text = asm::disassemble(program).unwrap();
+  let lines = text.lines().map(|l| l.split(';').next().unwrap().trim()).collect::<Vec<_>>();
+
+  assert_eq!(lines, ["mul [4], 3, [4]", "data 33", "arb -2", "l7:", "jt  1, l7"]);
+}
+
+#[test]
+fn assemble() {
+  let source = "
+    start: in  [value]      ; read a value
+           out [rb-1]
+           jf  [value], start
+           out \"hi\\n\"
+    value: data 0, \"ok\", start+1";
+
+  assert!(asm::assemble(source).is_err());
+
+  let source = source.replace("out \"hi\\n\"", "hlt");
+  let words = asm::assemble(&source).unwrap();
+  assert_eq!(words, [3, 8, 204, -1, 1006, 8, 0, 99, 0, 111, 107, 1]);
+}
+
+#[test]
+fn round_trip() {
+  let mut programs = vec![
+    ("day09/src/boost.txt".to_string(), include_str!("../../day09/src/boost.txt").to_string()),
+    ("day23/src/nic.txt".to_string(), include_str!("../../day23/src/nic.txt").to_string()),
+  ];
+
+  // Any other day's program that's been dropped in
+  let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+  for entry in std::fs::read_dir(root).unwrap() {
+    let path = entry.unwrap().path().join("src/program.txt");
+    if let Ok(program) = std::fs::read_to_string(&path) {
+      programs.push((path.display().to_string(), program));
+    }
+  }
+
+  assert!(!programs.is_empty());
+  for (name, program) in &programs {
+    let words = program.trim().split(',').map(|s| s.parse::<i64>().unwrap()).collect::<Vec<_>>();
+    let text = asm::disassemble(program).unwrap();
+    assert_eq!(asm::assemble(&text).unwrap(), words, "{}", name);
+  }
+}
+
+#[test]
+fn step() {
+  let program = "1101,2,3,7,4,7,99,0";
+  let mut vm = Vm::new(program).unwrap();
+
+  assert_eq!(vm.step().unwrap(), State::Running);
+  assert_eq!(vm.ip(), 4);
+  assert_eq!(vm.peek(7), Some(5));
+  assert_eq!(vm.step().unwrap(), State::Running);
+  assert_eq!(vm.read(), Some(5));
+  assert_eq!(vm.step().unwrap(), State::Done);
+}
+
+#[test]
+fn breakpoints() {
+  use debug::{Debugger, Stop};
+
+  let program = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+  let mut dbg = Debugger::new(Vm::new(program).unwrap());
+
+  dbg.set_breakpoint(11);
+  dbg.set_watchpoint(13);
+  assert_eq!(dbg.cont().unwrap(), Stop::Waiting);
+  dbg.vm_mut().write(5);
+  assert_eq!(dbg.cont().unwrap(), Stop::Watchpoint(13));
+  assert_eq!(dbg.vm().peek(13), Some(1));
+  assert_eq!(dbg.cont().unwrap(), Stop::Breakpoint(11));
+  assert_eq!(dbg.cont().unwrap(), Stop::Done);
+  assert_eq!(dbg.vm_mut().read(), Some(1));
+
+  // A breakpoint on the first instruction stops before it runs
+  let mut dbg = Debugger::new(Vm::new(program).unwrap());
+  dbg.set_breakpoint(0);
+  assert_eq!(dbg.cont().unwrap(), Stop::Breakpoint(0));
+  assert_eq!(dbg.cont().unwrap(), Stop::Waiting);
+}
+
+#[test]
+fn step_hook() {
+  use debug::Debugger;
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  let program = "1101,2,3,7,4,7,99,0";
+  let mut dbg = Debugger::new(Vm::new(program).unwrap());
+  let events = Rc::new(RefCell::new(Vec::new()));
+  let log = Rc::clone(&events);
+
+  dbg.on_step(move |_, e| log.borrow_mut().push((e.ip, e.write)));
+  dbg.cont().unwrap();
+  assert_eq!(*events.borrow(), [(0, Some(7)), (4, None), (6, None)]);
+}
+
+#[test]
+fn repl() {
+  use repl::Repl;
+
+  let program = "3,9,1001,9,1,9,4,9,99,0";
+  let mut out = Vec::new();
+  let input = ".b 6\n41\n.x 9\n.c\n.q\n42\n";
+
+  Repl::new(Vm::new(program).unwrap()).run(input.as_bytes(), &mut out).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  assert_eq!(out, "breakpoint at 6\n9: 42\n42\nhalted\n");
+
+  // A fault is reported and the session carries on
+  let program = "4,3,77,99";
+  let mut out = Vec::new();
+  let input = ".r\n.reset\n.q\n";
+
+  Repl::new(Vm::new(program).unwrap()).run(input.as_bytes(), &mut out).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  assert_eq!(out, concat!(
+    "99\nfault: unknown opcode 77 in 77 at ip 2\n",
+    "ip: 2 base: 0 state: Faulted\n",
+    "99\nfault: unknown opcode 77 in 77 at ip 2\n",
+  ));
+}
+
+#[test]
+fn trace() {
+  use trace::{JsonLines, Ring};
+
+  let program = "1101,2,3,7,4,7,99,0";
+  let mut ring = Ring::new(2);
+  let mut vm = Vm::new(program).unwrap();
+
+  vm.exec_traced(&mut ring).unwrap();
+  let ops = ring.records().map(|r| r.op).collect::<Vec<_>>();
+  assert_eq!(ops, [Op::Write, Op::Halt]);
+
+  let mut json = JsonLines(Vec::new());
+  vm.exec_traced(&mut json).unwrap();
+  let lines = String::from_utf8(json.0).unwrap();
+  assert_eq!(lines.lines().next().unwrap(), concat!(
+    r#"{"ip":0,"op":"add","args":["#,
+    r#"{"mode":"immediate","raw":2,"value":2},"#,
+    r#"{"mode":"immediate","raw":3,"value":3},"#,
+    r#"{"mode":"position","raw":7,"value":7}"#,
+    r#"],"write":{"addr":7,"value":5},"next":4}"#
+  ));
+
+  struct Full;
+  impl trace::Trace for Full {
+    fn record(&mut self, _: &trace::Record) -> anyhow::Result<()> {
+      Err(anyhow::anyhow!("sink full"))
+    }
+  }
+  let err = vm.exec_traced(&mut Full).unwrap_err();
+  assert_eq!(err, VmError::Trace { ip: 0, message: "sink full".to_string() });
+  assert_eq!(err.ip(), Some(0));
+  assert_eq!(vm.state(), State::Faulted);
+  assert_eq!(vm.fault(), Some(&err));
+
+  // A faulted vm stays faulted
+  assert_eq!(vm.cont_traced(&mut ring), Err(err.clone()));
+  assert_eq!(vm.cont(), Err(err));
+}
+
+#[test]
+fn profile() {
+  use trace::{Loop, Profiler};
+
+  // count down from 3, outputting each value
+  let program = "4,11,1001,11,-1,11,1005,11,0,99,0,3";
+  let mut profiler = Profiler::new();
+  let mut vm = Vm::new(program).unwrap();
+
+  vm.exec_traced(&mut profiler).unwrap();
+  assert_eq!(vm.drain(), [3, 2, 1]);
+  assert_eq!(profiler.total(), 10);
+  assert_eq!(profiler.hits(0), 3);
+  assert_eq!(profiler.loops(), [Loop { start: 0, end: 6, iterations: 2, hits: 9 }]);
+}
+
+#[test]
+fn snapshot() {
+  // echo doubled input until it reads zero
+  let program = "3,15,1005,15,6,99,1002,15,2,15,4,15,1105,1,0,0";
+  let mut vm = Vm::new(program).unwrap();
+  vm.exec().unwrap();
+
+  vm.write(3);
+  vm.write(4);
+  let saved = vm.snapshot();
+  vm.cont().unwrap();
+  assert_eq!(vm.drain(), [6, 8]);
+
+  vm.write(0);
+  assert_eq!(vm.cont().unwrap(), State::Done);
+
+  vm.restore(&saved);
+  assert_eq!(vm.state(), State::Waiting);
+  vm.cont().unwrap();
+  assert_eq!(vm.drain(), [6, 8]);
+}
+
+#[test]
+fn clone() {
+  let program = "3,9,1001,9,1,9,4,9,99,0";
+  let mut vm = Vm::new(program).unwrap();
+  let (mut stdin, _) = vm.pipes();
+  vm.exec().unwrap();
+
+  let mut other = vm.clone();
+  stdin.write(1);
+  other.write(10);
+  vm.cont().unwrap();
+  other.cont().unwrap();
+
+  assert_eq!(vm.drain(), [2]);
+  assert_eq!(other.drain(), [11]);
+  assert_eq!(other.peek(9), Some(11));
+  assert_eq!(vm.peek(9), Some(2));
+}
+
+#[test]
+fn send() {
+  fn is_send<T: Send>() {}
+  is_send::<Vm>();
+  is_send::<Pipe>();
+}
+
+#[test]
+fn network() {
+  use net::{Network, Schedule, Status};
+
+  // amplifier feedback loop from 2019 day 7
+  let program = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,\
+                 27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+  for schedule in [Schedule::Cooperative, Schedule::Threaded(3)] {
+    let mut network = Network::new();
+    for _ in 0..5 {
+      network.add(Vm::new(program).unwrap());
+    }
+    (0..5).for_each(|i| network.connect(i, (i + 1) % 5));
+    [9, 8, 7, 6, 5].iter().enumerate().for_each(|(i, &p)| network.vm_mut(i).write(p));
+    network.vm_mut(0).write(0);
+
+    assert_eq!(network.run(schedule).unwrap(), Status::Done);
+    assert_eq!(network.vm_mut(4).read(), Some(139629729));
+  }
+}
+
+#[test]
+fn network_idle() {
+  use net::{Network, Schedule, Status};
+
+  // output input + 1 forever, ignoring -1
+  let program = "3,20,1008,20,-1,21,1005,21,0,1001,20,1,20,4,20,1105,1,0,0,0,0,0";
+  let mut network = Network::new();
+  let a = network.add(Vm::new(program).unwrap());
+  let b = network.add(Vm::new(program).unwrap());
+  network.connect(a, b);
+  network.vm_mut(a).write(1);
+
+  assert_eq!(network.run(Schedule::Cooperative).unwrap(), Status::Idle);
+  assert_eq!(network.vm_mut(b).drain(), [3]);
+
+  // idle input keeps the vms reading but the network is still idle
+  network.set_idle_input(Some(-1));
+  assert_eq!(network.run(Schedule::Threaded(2)).unwrap(), Status::Idle);
+  network.vm_mut(a).write(5);
+  assert_eq!(network.run(Schedule::Threaded(2)).unwrap(), Status::Idle);
+  assert_eq!(network.vm_mut(b).drain(), [7]);
+}
+
+#[test]
+fn faults() {
+  let cases = [
+    ("42,0,99", VmError::UnknownOpcode { ip: 0, inst: 42 }),
+    ("1101,1,2,5,301,99", VmError::InvalidMode { ip: 4, inst: 301 }),
+    ("11101,1,2,5,99", VmError::ImmediateWrite { ip: 0, inst: 11101 }),
+    ("104,0,4,-3,99", VmError::NegativeAddress { ip: 2, inst: 4, addr: -3 }),
+    ("104,0,1105,1,-1", VmError::NegativeAddress { ip: 2, inst: 1105, addr: -1 }),
+  ];
+
+  for (program, error) in cases.iter() {
+    let mut vm = Vm::new(program).unwrap();
+    assert_eq!(vm.exec().unwrap_err(), *error);
+    assert_eq!(vm.state(), State::Faulted);
+    assert_eq!(vm.fault(), Some(error));
+    assert_eq!(vm.ip(), error.ip().unwrap());
+    assert_eq!(vm.cont().unwrap_err(), *error);
+  }
+}
+
+#[test]
+fn input_exhausted() {
+  let program = "3,5,3,5,99,0";
+  let mut vm = Vm::new(program).unwrap();
+  vm.write(1);
+
+  assert_eq!(vm.finish().unwrap_err(), VmError::InputExhausted { ip: 2, inst: 3 });
+  vm.write(2);
+  assert!(vm.finish().is_ok());
+  assert!(vm.is_done());
+}
+
+#[test]
+fn load_errors() {
+  assert_eq!(Vm::new("1,2,x").unwrap_err(), VmError::Parse { pos: 2, text: "x".to_string() });
+  assert_eq!(Vm::with_limit("1,2,3", 2).unwrap_err(), VmError::TooLarge { size: 3, limit: 2 });
+}
+
+#[test]
+fn ascii() {
+  use ascii::Ascii;
+
+  // print "hi" and a prompt, then answer with the input + 1000
+  let program = "104,104,104,105,104,10,104,62,3,20,1001,20,1000,20,4,20,99,0,0,0,0";
+  let mut io = Ascii::new(Vm::new(program).unwrap());
+
+  assert_eq!(io.exec().unwrap(), State::Waiting);
+  assert_eq!(io.read_line(), Some("hi".to_string()));
+  assert_eq!(io.read_line(), None);
+
+  io.write_line("A");
+  assert_eq!(io.cont().unwrap(), State::Done);
+  assert_eq!(io.output(), (">".to_string(), Some(1065)));
+}
+
+#[test]
+fn screen() {
+  use screen::Screen;
+
+  let mut screen = Screen::new();
+  screen.draw(&[1, 2, 3, -1, 0, 12345, 6, 5, 4, 200, -300, 2]);
+  assert_eq!(screen.get(1, 2), Some(3));
+  assert_eq!(screen.get(-1, 0), None);
+  assert_eq!(screen.score(), Some(12345));
+  assert_eq!(screen.bounds(), Some(((1, -300), (200, 5))));
+  assert_eq!((screen.width(), screen.height()), (200, 306));
+  assert_eq!(screen.find(4).collect::<Vec<_>>(), [(6, 5)]);
+
+  let screen = Screen::from_ascii("#.#\n.^.\n");
+  assert_eq!(screen.count('#' as i64), 2);
+  assert_eq!(screen.to_string(), "#.#\n.^.\n");
+  assert_eq!(screen.render(|v| if v == Some('#' as i64) { 'X' } else { ' ' }), "X X\n   \n");
+}
+
+#[test]
+fn self_modifying() {
+  // output 1, overwrite that instruction with a halt and jump back
+  let program = "104,1,1101,99,0,0,1105,1,0";
+  let mut vm = Vm::new(program).unwrap();
+
+  assert_eq!(vm.exec().unwrap(), State::Done);
+  assert_eq!(vm.drain(), [1]);
+  assert_eq!(vm.peek(0), Some(99));
+
+  // reset brings back the original code
+  assert_eq!(vm.exec().unwrap(), State::Done);
+  assert_eq!(vm.drain(), [1]);
+
+  // restoring the original code evicts the halt decoded at 0
+  let mut vm = Vm::new(program).unwrap();
+  let start = vm.snapshot();
+  vm.exec().unwrap();
+  vm.drain();
+  vm.restore(&start);
+  assert_eq!(vm.step().unwrap(), State::Running);
+  assert_eq!(vm.drain(), [1]);
+}
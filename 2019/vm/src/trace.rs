@@ -0,0 +1,228 @@
+//! Execution tracing and profiling.
+//!
+//! `Vm::exec_traced` and `Vm::cont_traced` hand a `Record` for every
+//! executed instruction to a `Trace` sink. `Ring` keeps the most recent
+//! records, `JsonLines` writes one JSON object per instruction and
+//! `Profiler` counts hits per address and iterations of backward jumps.
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Write;
+
+use crate::{getm, getop, Mode, Op, State, Vm, VmError};
+
+/// A decoded operand: the raw parameter, and the value read or the
+/// address written for a write target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arg {
+    pub mode: Mode,
+    pub raw: i64,
+    pub value: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub ip: usize,
+    pub op: Op,
+    pub args: Vec<Arg>,
+    /// Address and value written to memory, if any.
+    pub write: Option<(usize, i64)>,
+    /// Instruction pointer after execution.
+    pub next: usize,
+}
+
+impl Record {
+    pub fn to_json(&self) -> String {
+        let args = self.args.iter()
+            .map(|a| format!(
+                r#"{{"mode":"{}","raw":{},"value":{}}}"#,
+                format!("{:?}", a.mode).to_lowercase(), a.raw, a.value
+            ))
+            .collect::<Vec<_>>()
+            .join(",");
+        let write = self.write
+            .map_or("null".to_string(), |(addr, v)| format!(r#"{{"addr":{},"value":{}}}"#, addr, v));
+
+        format!(
+            r#"{{"ip":{},"op":"{}","args":[{}],"write":{},"next":{}}}"#,
+            self.ip, self.op.mnemonic(), args, write, self.next
+        )
+    }
+}
+
+pub trait Trace {
+    fn record(&mut self, rec: &Record) -> Result<()>;
+}
+
+/// Keeps the last `capacity` records.
+#[derive(Debug)]
+pub struct Ring {
+    records: VecDeque<Record>,
+    capacity: usize,
+}
+
+impl Ring {
+    pub fn new(capacity: usize) -> Self {
+        Ring { records: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter()
+    }
+}
+
+impl Trace for Ring {
+    fn record(&mut self, rec: &Record) -> Result<()> {
+        if self.capacity > 0 {
+            if self.records.len() == self.capacity {
+                self.records.pop_front();
+            }
+            self.records.push_back(rec.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Writes each record as a line of JSON.
+#[derive(Debug)]
+pub struct JsonLines<W: Write>(pub W);
+
+impl<W: Write> Trace for JsonLines<W> {
+    fn record(&mut self, rec: &Record) -> Result<()> {
+        writeln!(self.0, "{}", rec.to_json())?;
+        Ok(())
+    }
+}
+
+/// A loop closed by a backward jump from `end` to `start`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    pub iterations: u64,
+    /// Instructions executed inside start..=end.
+    pub hits: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    hits: HashMap<usize, u64>,
+    jumps: HashMap<(usize, usize), u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn hits(&self, addr: usize) -> u64 {
+        self.hits.get(&addr).copied().unwrap_or(0)
+    }
+
+    /// The `n` most executed addresses.
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut hits = self.hits.iter().map(|(&a, &c)| (a, c)).collect::<Vec<_>>();
+        hits.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits.truncate(n);
+        hits
+    }
+
+    /// Loops ordered by the number of instructions executed inside them.
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops = self.jumps.iter()
+            .map(|(&(start, end), &iterations)| {
+                let hits = self.hits.iter()
+                    .filter(|(a, _)| (start..=end).contains(a))
+                    .map(|(_, c)| c)
+                    .sum();
+                Loop { start, end, iterations, hits }
+            })
+            .collect::<Vec<_>>();
+        loops.sort_unstable_by(|a, b| b.hits.cmp(&a.hits).then(a.start.cmp(&b.start)));
+        loops
+    }
+}
+
+impl Trace for Profiler {
+    fn record(&mut self, rec: &Record) -> Result<()> {
+        self.total += 1;
+        *self.hits.entry(rec.ip).or_insert(0) += 1;
+        if rec.op != Op::Halt && rec.next <= rec.ip {
+            *self.jumps.entry((rec.next, rec.ip)).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions: {}", self.total)?;
+        writeln!(f, "hot addresses:")?;
+        for (addr, count) in self.hottest(10) {
+            writeln!(f, "  {:>6}: {}", addr, count)?;
+        }
+        writeln!(f, "hot loops:")?;
+        for l in self.loops().iter().take(10) {
+            writeln!(f, "  {:>6}..={:<6} iterations: {} instructions: {}",
+                l.start, l.end, l.iterations, l.hits)?;
+        }
+        Ok(())
+    }
+}
+
+impl Vm {
+    pub(crate) fn run_traced(&mut self, tracer: &mut dyn Trace) -> Result<State, VmError> {
+        self.state = State::Running;
+        while self.state == State::Running {
+            let ip = self.ip;
+            // A fault while decoding is reported by tick
+            let (op, args) = match self.decode() {
+                Some(decoded) => decoded,
+                None => {
+                    self.tick()?;
+                    continue
+                }
+            };
+
+            let write = self.tick()?
+                .map(|addr| (addr, self.memory.get(addr).unwrap_or_default()));
+            if self.state != State::Waiting {
+                let rec = Record { ip, op, args, write, next: self.ip };
+                if let Err(e) = tracer.record(&rec) {
+                    let e = VmError::Trace { ip, message: e.to_string() };
+                    self.state = State::Faulted;
+                    self.fault = Some(e.clone());
+                    return Err(e)
+                }
+            }
+        }
+
+        Ok(self.state)
+    }
+
+    fn decode(&self) -> Option<(Op, Vec<Arg>)> {
+        let ip = self.ip;
+        let (op, mut inst) = getop(self.load(ip as i64).ok()?)?;
+        let mut args = Vec::with_capacity(op.params());
+        for i in 0..op.params() {
+            let (mode, rest) = getm(inst)?;
+            inst = rest;
+            let pos = ip + i + 1;
+            let raw = self.load(pos as i64).ok()?;
+            let value = if op.writes() && i + 1 == op.params() {
+                if mode == Mode::Relative { raw + self.base } else { raw }
+            } else {
+                self.getv(mode, pos).ok()?
+            };
+            args.push(Arg { mode, raw, value });
+        }
+
+        Some((op, args))
+    }
+}
@@ -1,3 +1,6 @@
+use regvm::handheld::{self, Inst};
+use regvm::Stop;
+
 fn main()
 {
     use std::time::Instant;
@@ -13,84 +16,49 @@ fn main()
     println!("Part 2: {} ({:?})", result, t.elapsed());
 }
 
-fn part_one(input: &str) -> i32
+fn part_one(input: &str) -> i64
 {
     let program = load(input);
-    exec_one(&program).unwrap()
+    exec(&program).unwrap_err()
 }
 
-fn part_two(input: &str) -> i32
+fn part_two(input: &str) -> i64
 {
     let mut program = load(input);
     let ips = (0..program.len())
-        .filter(|ip| program[*ip].0 == "jmp" || program[*ip].0 == "nop")
+        .filter(|ip| !matches!(program[*ip], Inst::Acc(_)))
         .collect::<Vec<_>>();
 
     for ip in ips {
-        let inst = program[ip].0;
-        program[ip].0 = if inst == "jmp" { "nop" } else { "jmp" };
-        if let Some(n) = exec_two(&program) {
+        let inst = program[ip];
+        program[ip] = match inst {
+            Inst::Jmp(n) => Inst::Nop(n),
+            Inst::Nop(n) => Inst::Jmp(n),
+            Inst::Acc(_) => unreachable!()
+        };
+        if let Ok(n) = exec(&program) {
             return n
         }
-        program[ip].0 = inst
+        program[ip] = inst
     }
     
     0
 }
 
-fn load(input: &str) -> Vec<(&str, i32)>
+fn load(input: &str) -> Vec<Inst>
 {
-    input.lines()
-        .map(|line| {
-            let (op, v) = line.split_once(' ').unwrap();
-            let v = v.parse::<i32>().unwrap();
-            (op, v)
-        })
-        .collect()
-}
-
-fn exec_one(program: &[(&str, i32)]) -> Option<i32>
-{
-    use std::collections::HashSet;
-
-    let mut ip   = 0;
-    let mut acc  = 0;
-    let mut seen = HashSet::new();
-    loop {
-        if seen.insert(ip) {
-            ip = match program[ip] {
-                ("acc", n) => { acc += n; ip + 1 },
-                ("jmp", n) => (ip as i32 + n) as usize,
-                ("nop", _) => ip + 1,
-                _ => unreachable!()
-            }
-        } else {
-            return Some(acc)
-        }
-    }
+    handheld::parse(input).unwrap()
 }
 
-fn exec_two(program: &[(&str, i32)]) -> Option<i32>
+/// The accumulator when the program terminates, or as an error when
+/// it's about to loop.
+fn exec(program: &[Inst]) -> Result<i64, i64>
 {
-    use std::collections::HashSet;
-
-    let mut ip   = 0;
-    let mut acc  = 0;
-    let mut seen = HashSet::new();
-    while ip < program.len() {
-        if seen.insert(ip) {
-            ip = match program[ip] {
-                ("acc", n) => { acc += n; ip + 1 },
-                ("jmp", n) => (ip as i32 + n) as usize,
-                ("nop", _) => ip + 1,
-                _ => unreachable!()
-            }
-        } else {
-            return None
-        }
+    let mut m = handheld::machine(program.to_vec());
+    match m.run_until_repeat() {
+        Stop::Halted => Ok(m.reg(handheld::ACC)),
+        _ => Err(m.reg(handheld::ACC)),
     }
-
-    Some(acc)
 }
 
 
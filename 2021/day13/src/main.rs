@@ -15,9 +15,7 @@ fn main() {
     let t1 = Instant::now();
     let folded = part_two(&dots, &folds);
     let t2 = Instant::now();
-    println!("Part 2: {} {:?}", folded.len(), t2 - t1);
-
-//    print(&folded);
+    println!("Part 2: {} {} {:?}", folded.len(), code(&folded), t2 - t1);
 }
 
 #[derive(Debug)]
@@ -73,19 +71,9 @@ fn fold(axis: &Fold, dot: &(u32, u32)) -> (u32, u32) {
     }
 }
 
-#[allow(dead_code)]
-fn print(dots: &HashSet<(u32, u32)>) {
-    let mut v = dots.iter().collect::<Vec<_>>();
-    v.sort();
-    
-    (0..6).for_each(|y| {
-        (0..40).for_each(|x| {
-            print!(".");
-            let c = if dots.contains(&(x, y)) { '#' } else { '.' };
-            print!("{c}");
-        });
-        println!();
-    })
+fn code(dots: &HashSet<(u32, u32)>) -> String {
+    let paper = ocr::Bitmap::from_points(dots.iter().map(|(x, y)| (*x as i64, *y as i64)));
+    ocr::read(&paper).unwrap_or_else(|e| panic!("{e}"))
 }
 
 
@@ -104,7 +92,6 @@ mod tests {
 
         let folded = part_two(&dots, &folds);
         assert_eq!(folded.len(), 95);
-
-        // HKUJGAJZ
+        assert_eq!(code(&folded), "HKUJGAJZ");
     }
 }
\ No newline at end of file
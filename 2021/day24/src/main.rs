@@ -1,3 +1,5 @@
+use regvm::alu::{self, Inst};
+
 fn main() {
     use std::fs;
     use std::time::Instant;
@@ -16,195 +18,182 @@ fn main() {
     println!("Part 2: {} {:?}", model, t2 - t1);
 }
 
-fn load(input: &str) -> Vec<Cmd> {
-    use Cmd::*;
-
-    input.lines().map(|s| {
-        let mut it = s.split(' ');
-        let cmd = it.next().unwrap();
-        let c = it.next().unwrap().chars().next().unwrap();
-        match cmd {
-            "inp" => Inp(c),
-            "add" => Add(c, parse(it.next())),
-            "mul" => Mul(c, parse(it.next())),
-            "div" => Div(c, parse(it.next())),
-            "mod" => Mod(c, parse(it.next())),
-            "eql" => Eql(c, parse(it.next())),
-                _ => panic!("Unknown command: {cmd}"),
-        }
-    })
-    .collect()
+fn load(input: &str) -> Vec<Inst> {
+    alu::parse(input).unwrap()
 }
 
-fn parse(opt: Option<&str>) -> Value {
-    let s = opt.unwrap();
-    s.parse().map_or_else(
-        |_| Value::Variable(s.chars().next().unwrap()),
-        Value::Number,
-    )
+fn part_one(cmds: &[Inst]) -> i64 {
+    let monad = Monad::new(cmds);
+    let model = monad.model(Goal::Largest).expect("No valid model number");
+    assert_eq!(monad.run(&model), 0);
+
+    number(&model)
 }
 
-fn part_one(cmds: &[Cmd]) -> i64 {
-    // Based on the rules we can determine the max values
-    // for most inputs so we just iterate down from 99 to
-    // 11 for the two unknown values til we find one resulting
-    // in zero.
-    //
-    // W's are inputs not array location (so "off-by-one")
-    //  w5 == w4 - 8  => w5  = 1, w4 = 9
-    //  w7 == w6 - 4  => w7  = 5, w6 = 9
-    //  w8 == w3 + 5  => w8  = 9, w3 = 4
-    // w10 == w9      => w10 = 9, w9 = 9
-    // w13 == w2 + 1  => w13 = 9, w2 = 8
-    // w14 == w1 - 5  => w14 = 4, w1 = 9
-    //
-    // 9849195999XX94
-
-    let mut monad = Monad::new(cmds);
-
-    let mut n = 99;
-    let mut digits = [9,8,4,9,1,9,5,9,9,9,0,0,9,4];
-
-    digits[10] = n / 10;
-    digits[11] = n % 10;
-    while monad.run(&digits) != 0 && n > 10 {
-        n -= 1;
-        digits[10] = n / 10;
-        digits[11] = n % 10;
-    }
-    
-    digits.iter().fold(0, |v, n| v * 10 + n)
+fn part_two(cmds: &[Inst]) -> i64 {
+    let monad = Monad::new(cmds);
+    let model = monad.model(Goal::Smallest).expect("No valid model number");
+    assert_eq!(monad.run(&model), 0);
+
+    number(&model)
 }
 
-fn part_two(cmds: &[Cmd]) -> i64 {
-    // Now use the rules to determine the min values.
-    //
-    // W's are inputs not array locations (ie "off-by-one")
-    //  w5 == w4 - 8  => w4  = 9, w5 = 1
-    //  w7 == w6 - 4  => w7  = 1, w6 = 5
-    //  w8 == w3 + 5  => w3  = 1, w8 = 6
-    // w10 == w9      => w10 = 1, w9 = 1
-    // w13 == w2 + 1  => w13 = 2, w2 = 1
-    // w14 == w1 - 5  => w14 = 1, w1 = 6
-
-    // 6119151611XX21
-
-    let mut monad = Monad::new(cmds);
-
-    let mut n = 11;
-    let mut digits = [6,1,1,9,1,5,1,6,1,1,0,0,2,1];
-
-    digits[10] = n / 10;
-    digits[11] = n % 10;
-    while monad.run(&digits) != 0 && n < 100 {
-        n += 1;
-        digits[10] = n / 10;
-        digits[11] = n % 10;
-    }
-    
+fn number(digits: &[i64]) -> i64 {
     digits.iter().fold(0, |v, n| v * 10 + n)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Goal {
+    Largest,
+    Smallest,
+}
 
-#[derive(Clone, Debug)]
-enum Value {
-    Number(i64),
-    Variable(char),
+// The program is 14 blocks, one per digit, differing only in three
+// constants. z is a stack of base 26 digits: blocks dividing z by 1
+// push w + y, and blocks dividing by 26 pop the top value and push
+// again unless w == top + x. For z to end at zero every pop has to
+// match, so each popping digit is tied to the digit that pushed:
+//
+//   w_pop == w_push + y_push + x_pop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Block {
+    div: i64,
+    x: i64,
+    y: i64,
 }
 
-#[derive(Clone, Debug)]
-enum Cmd {
-    Inp(char),
-    Add(char, Value),
-    Mul(char, Value),
-    Div(char, Value),
-    Mod(char, Value),
-    Eql(char, Value),
+impl Block {
+    const LEN: usize = 18;
+
+    fn code(&self) -> [Inst; Block::LEN] {
+        use regvm::Operand::{Imm, Reg};
+        use Inst::*;
+
+        let [w, x, y, z] = ['w', 'x', 'y', 'z'].map(alu::reg);
+        [
+            Inp(w),
+            Mul(x, Imm(0)), Add(x, Reg(z)), Mod(x, Imm(26)),
+            Div(z, Imm(self.div)),
+            Add(x, Imm(self.x)), Eql(x, Reg(w)), Eql(x, Imm(0)),
+            Mul(y, Imm(0)), Add(y, Imm(25)), Mul(y, Reg(x)), Add(y, Imm(1)),
+            Mul(z, Reg(y)),
+            Mul(y, Imm(0)), Add(y, Reg(w)), Add(y, Imm(self.y)), Mul(y, Reg(x)),
+            Add(z, Reg(y)),
+        ]
+    }
+
+    /// Pull the constants out of a block, checking it has the
+    /// expected shape.
+    fn decode(code: &[Inst]) -> Option<Block> {
+        use regvm::Operand::Imm;
+
+        let constant = |i: usize| match code.get(i) {
+            Some(Inst::Div(_, Imm(n)) | Inst::Add(_, Imm(n))) => Some(*n),
+            _ => None,
+        };
+
+        let block = Block { div: constant(4)?, x: constant(5)?, y: constant(15)? };
+        (code == block.code()).then_some(block)
+    }
 }
 
 struct Monad {
-    cmds: Vec<Cmd>,
-    vars: [i64;4],
+    cmds: Vec<Inst>,
+    blocks: Vec<Block>,
 }
 
 impl Monad {
-    fn new(cmds: &[Cmd]) -> Monad {
-        Monad {
-            cmds: cmds.to_vec(),
-            vars: [0;4],
-        }
+    fn new(cmds: &[Inst]) -> Monad {
+        let blocks = cmds.chunks(Block::LEN)
+            .enumerate()
+            .map(|(i, code)| Block::decode(code)
+                .unwrap_or_else(|| panic!("Block {} isn't a MONAD digit check", i + 1))
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 14, "Expected 14 digits");
+
+        Monad { cmds: cmds.to_vec(), blocks }
     }
 
-    #[allow(dead_code)]
-    fn run(&mut self, model: &[i64;14]) -> i64 {
-        self.execute(model, false)
-    }
+    /// Pairs of pushing and popping digits with the difference
+    /// between them: w[pop] == w[push] + diff.
+    fn constraints(&self) -> Option<Vec<(usize, usize, i64)>> {
+        let mut stack = vec![];
+        let mut pairs = vec![];
+
+        for (i, b) in self.blocks.iter().enumerate() {
+            match b.div {
+                // A digit can't match x > 9, so this always pushes.
+                1 if b.x > 9 => stack.push((i, b.y)),
+                26 => {
+                    let (j, y) = stack.pop()?;
+                    pairs.push((j, i, y + b.x));
+                },
+                _ => return None,
+            }
+        }
 
-    #[allow(dead_code)]
-    fn debug(&mut self, model: &[i64;14]) -> i64 {
-        self.execute(model, true)
+        stack.is_empty().then_some(pairs)
     }
 
-    fn execute(&mut self, model: &[i64;14], debug: bool) -> i64 {
-        use Cmd::*;
-
-        let mut i = 0;
-        self.vars = [0;4];
+    fn model(&self, goal: Goal) -> Option<[i64;14]> {
+        let mut digits = [0;14];
 
-        let cmds = self.cmds.to_vec();
-        cmds.iter().for_each(|cmd| {
-            match cmd {
-                Inp(a) => {
-                    self.setv(a, model[i]);
-                    i += 1
-                },
-                Add(a, b) => {
-                    let n = self.getv(a);
-                    self.setv(a, n + self.val(b))
-                },
-                Mul(a, b) => {
-                    let n = self.getv(a);
-                    self.setv(a, n * self.val(b))
-                },
-                Div(a, b) => {
-                    let n = self.getv(a);
-                    self.setv(a, n / self.val(b))
-                },
-                Mod(a, b) => {
-                    let n = self.getv(a);
-                    self.setv(a, n % self.val(b))
-                },
-                Eql(a, b) => {
-                    let n = self.getv(a);
-                    self.setv(a, (n == self.val(b)) as i64)
-                },
+        for (push, pop, diff) in self.constraints()? {
+            if diff.abs() > 8 {
+                return None
+            }
+            digits[push] = match goal {
+                Goal::Largest  => 9.min(9 - diff),
+                Goal::Smallest => 1.max(1 - diff),
             };
+            digits[pop] = digits[push] + diff;
+        }
 
-            if debug { println!("{:?} => {:?}", cmd, self.vars); }
-        });
-
-        self.getv(&'z')
+        Some(digits)
     }
 
-    #[allow(dead_code)]
-    fn result(&self) -> i64 {
-        self.getv(&'z')
+    /// Run the program on a model number, returning z.
+    fn run(&self, model: &[i64;14]) -> i64 {
+        let mut m = alu::machine(self.cmds.clone());
+        model.iter().for_each(|d| m.write(*d));
+        m.run();
+        m.reg(alu::reg('z'))
     }
+}
 
-    fn val(&self, val: &Value) -> i64 {
-        match val {
-            Value::Number(n)   => *n,
-            Value::Variable(c) => self.getv(c),
-        }
-    }
 
-    fn getv(&self, var: &char) -> i64 {
-        let i = *var as usize - 'w' as usize;
-        self.vars[i]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn it_works() {
+        let input = fs::read_to_string("./input.txt").unwrap();
+        let cmds = load(&input);
+
+        assert_eq!(part_one(&cmds), 98491959997994);
+        assert_eq!(part_two(&cmds), 61191516111321);
     }
 
-    fn setv(&mut self, var: &char, val: i64) {
-        let i = *var as usize - 'w' as usize;
-        self.vars[i] = val;
+    #[test]
+    fn analysis() {
+        // The constants from notes.txt.
+        let blocks = [
+            (1, 10, 2), (1, 15, 16), (1, 14, 9), (1, 15, 0), (26, -8, 1),
+            (1, 10, 12), (26, -16, 6), (26, -4, 6), (1, 11, 3), (26, -3, 5),
+            (1, 12, 9), (26, -7, 3), (26, -15, 2), (26, -7, 3),
+        ];
+        let cmds = blocks.iter()
+            .flat_map(|&(div, x, y)| Block { div, x, y }.code())
+            .collect::<Vec<_>>();
+        let monad = Monad::new(&cmds);
+
+        assert_eq!(monad.blocks[4], Block { div: 26, x: -8, y: 1 });
+        assert_eq!(monad.constraints().unwrap()[0], (3, 4, -8));
+        assert_eq!(part_one(&cmds), 98491959997994);
+        assert_eq!(part_two(&cmds), 61191516111321);
+        assert_ne!(monad.run(&[9;14]), 0);
     }
 }
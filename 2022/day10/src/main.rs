@@ -33,17 +33,18 @@ fn part_one(input: &str) -> i32 {
 
 fn part_two(input: &str) -> String {
     let mut vm = Vm::new(input);
+    let mut crt = String::new();
 
     (0..240)
         .for_each(|cycle| {
             let (x, h_pos) = (vm.getx(), cycle % 40);
-            let pixel = if h_pos >= x-1 && h_pos <= x+1 { '#' } else { ' ' };
-            if h_pos == 39 { println!("{pixel}") } else { print!("{pixel}")}
+            crt.push(if h_pos >= x-1 && h_pos <= x+1 { '#' } else { '.' });
+            if h_pos == 39 { crt.push('\n') }
 
             vm.do_tick();
         });
 
-    "PBZGRAZA".into()
+    ocr::read_text(&crt).unwrap_or_else(|e| panic!("{e}"))
 }
 
 
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+type Vector = [i32; 3];
+
+fn neg(v: Vector) -> Vector {
+    [-v[0], -v[1], -v[2]]
+}
+
+/// Which way a face points once folded, and which ways its map right
+/// and down point.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    normal: Vector,
+    right: Vector,
+    down: Vector,
+}
+
+impl Frame {
+    /// The face next to this one on the map in direction `dir`, folded
+    /// over the shared edge.
+    fn roll(&self, dir: usize) -> Frame {
+        let Frame { normal, right, down } = *self;
+        match dir {
+            0 => Frame { normal: right, right: neg(normal), down },
+            1 => Frame { normal: down, right, down: neg(normal) },
+            2 => Frame { normal: neg(right), right: normal, down },
+            _ => Frame { normal: neg(down), right, down: normal },
+        }
+    }
+
+    /// Which way the edge in direction `dir` faces, with right: 0,
+    /// down: 1, left: 2, up: 3.
+    fn edge(&self, dir: usize) -> Vector {
+        [self.right, self.down, neg(self.right), neg(self.down)][dir]
+    }
+
+    /// Which way the tiles along the edge in direction `dir` count up.
+    fn along(&self, dir: usize) -> Vector {
+        if dir.is_multiple_of(2) { self.down } else { self.right }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Wrap {
+    face: usize,
+    dir: i32,
+    flip: bool,
+}
+
+/// The map folded into a cube: the faces' top left corners on the map,
+/// in reading order, and where walking off each edge comes back on.
+#[derive(Debug)]
+pub struct Cube {
+    pub size: usize,
+    pub faces: Vec<(usize, usize)>,
+    wraps: HashMap<(usize, i32), Wrap>,
+}
+
+impl Cube {
+    pub fn fold(map: &[Vec<char>]) -> Cube {
+        let tiles = map.iter().flatten().filter(|c| **c != ' ').count();
+        let size = (1..).find(|n| 6 * n * n >= tiles).unwrap();
+        assert!(6 * size * size == tiles, "{tiles} tiles don't make a cube");
+
+        let faces = (0..map.len()).step_by(size)
+            .flat_map(|row| (0..map[row].len()).step_by(size).map(move |col| (row, col)))
+            .filter(|(row, col)| map[*row][*col] != ' ')
+            .collect::<Vec<_>>();
+        assert!(faces.len() == 6, "The map has {} faces, not 6", faces.len());
+
+        // Lay the first face down and roll the rest over from their
+        // neighbours on the map.
+        let mut frames = HashMap::from([
+            (0, Frame { normal: [0, 0, 1], right: [1, 0, 0], down: [0, 1, 0] })
+        ]);
+        let mut q = vec![0];
+        while let Some(face) = q.pop() {
+            let (row, col) = faces[face];
+            for dir in 0..4 {
+                let (dr, dc) = crate::OFFSETS[dir];
+                let next = (row as i32 + dr * size as i32, col as i32 + dc * size as i32);
+                let Some(neighbour) = faces.iter().position(|f| (f.0 as i32, f.1 as i32) == next) else {
+                    continue
+                };
+                if !frames.contains_key(&neighbour) {
+                    frames.insert(neighbour, frames[&face].roll(dir));
+                    q.push(neighbour);
+                }
+            }
+        }
+
+        let on = |normal: Vector| (0..6).find(|f| frames[f].normal == normal)
+            .unwrap_or_else(|| panic!("The map doesn't fold into a cube"));
+
+        // Walking off an edge comes onto the face it folds against, over
+        // the edge of that face pointing back, and heading away from it.
+        let wraps = (0..6)
+            .flat_map(|face| (0..4).map(move |dir| (face, dir)))
+            .map(|(face, dir)| {
+                let from = frames[&face];
+                let to = on(from.edge(dir));
+                let edge = (0..4).find(|d| frames[&to].edge(*d) == from.normal).unwrap();
+                let flip = frames[&to].along(edge) != from.along(dir);
+
+                ((face, dir as i32), Wrap { face: to, dir: ((edge + 2) % 4) as i32, flip })
+            })
+            .collect();
+
+        Cube { size, faces, wraps }
+    }
+
+    /// Walk off `face` heading `dir` from the `x`th tile along the
+    /// edge, returning the face, direction, row and column after.
+    pub fn wrap(&self, face: usize, dir: i32, x: usize) -> (usize, i32, usize, usize) {
+        let Wrap { face, dir, flip } = self.wraps[&(face, dir)];
+        let last = self.size - 1;
+        let x = if flip { last - x } else { x };
+
+        let (row, col) = match dir {
+            0 => (x, 0),
+            1 => (0, x),
+            2 => (x, last),
+            _ => (last, x),
+        };
+        (face, dir, row, col)
+    }
+}
@@ -1,7 +1,5 @@
-use std::collections::HashMap;
-use lazy_static::lazy_static;
-
-const N: usize = 50;
+mod cube;
+use cube::Cube;
 
 fn main() {
     use std::time::Instant;
@@ -35,21 +33,21 @@ fn part_one(input: &str) -> usize {
 
 fn part_two(input: &str) -> usize {
     let (tiles, actions) = load(input);
-    let faces = vec![(0, N), (0, 2*N), (N, N), (2*N, 0), (2*N, N), (3*N, 0)];
+    let cube = Cube::fold(&tiles);
 
     // right: 0, down: 1, left: 2, up: 3
     let mut pos = (0, 0i32, 0, 0);
     actions.iter()
         .for_each(|a| {
             pos = match a {
-                Action::Move(n)   => move_3d(pos, *n, &tiles, &faces),
+                Action::Move(n)   => move_3d(pos, *n, &tiles, &cube),
                 Action::TurnLeft  => (pos.0, (pos.1 - 1).rem_euclid(4), pos.2, pos.3),
                 Action::TurnRight => (pos.0, (pos.1 + 1).rem_euclid(4), pos.2, pos.3),
             };
         });
 
-    let row = pos.2 + 1 + faces[pos.0].0;
-    let col = pos.3 + 1 + faces[pos.0].1;
+    let row = pos.2 + 1 + cube.faces[pos.0].0;
+    let col = pos.3 + 1 + cube.faces[pos.0].1;
 
     1000 * row + 4 * col + pos.1 as usize
 }
@@ -110,57 +108,42 @@ fn move_3d(
     mut pos: (usize, i32, usize, usize),
     mut steps: i32,
     map: &[Vec<char>],
-    faces: &[(usize, usize)]
+    cube: &Cube,
 ) -> (usize, i32, usize, usize) {
-    let (mut face, mut dir, mut row, mut col) = next_tile_3d(pos);
+    let faces = &cube.faces;
+    let (mut face, mut dir, mut row, mut col) = next_tile_3d(pos, cube);
     let mut c = map[row + faces[face].0][col + faces[face].1];
 
     while steps > 0 && c != '#' {
         steps -= 1;
         pos = (face, dir, row, col);
 
-        (face, dir, row, col) = next_tile_3d(pos);
+        (face, dir, row, col) = next_tile_3d(pos, cube);
         c = map[row + faces[face].0][col + faces[face].1];
     }
 
     pos
 }
 
-fn next_tile_3d((face, dir, row, col): (usize, i32, usize, usize)) -> (usize, i32, usize, usize)
+fn next_tile_3d(
+    (face, dir, row, col): (usize, i32, usize, usize),
+    cube: &Cube,
+) -> (usize, i32, usize, usize)
 {
     let (delta_r, delta_c) = OFFSETS[dir as usize];
     let row_next = row as i32 + delta_r;
     let col_next = col as i32 + delta_c;
+    let n = cube.size as i32;
 
-    if !(0..N as i32).contains(&row_next) {
-        let (f, d, r, c) = *FACES.get(&(face, dir)).unwrap();
-        (f, d, r(col), c(col))
-    } else if !(0..N as i32).contains(&col_next) {
-        let (f, d, r, c) = *FACES.get(&(face, dir)).unwrap();
-        (f, d, r(row), c(row))
+    if !(0..n).contains(&row_next) {
+        cube.wrap(face, dir, col)
+    } else if !(0..n).contains(&col_next) {
+        cube.wrap(face, dir, row)
     } else {
         (face, dir, row_next as usize, col_next as usize)
     }
 }
 
-const X: fn(usize) -> usize = |x| x;
-const Z: fn(usize) -> usize = |_| 0;
-const W: fn(usize) -> usize = |_| N - 1;
-const M: fn(usize) -> usize = |x| N - 1 - x;
-
-type Transition = (usize, i32, fn(usize) -> usize, fn(usize) -> usize);
-lazy_static! {
-    static ref FACES: HashMap<(usize, i32), Transition> = 
-        HashMap::from([
-            ((0, 0), (1, 0, X, Z)), ((0, 1), (2, 1, Z, X)), ((0, 2), (3, 0, M, Z)), ((0, 3), (5, 0, X, Z)),
-            ((1, 0), (4, 2, M, W)), ((1, 1), (2, 2, X, W)), ((1, 2), (0, 2, X, W)), ((1, 3), (5, 3, W, X)),
-            ((2, 0), (1, 3, W, X)), ((2, 1), (4, 1, Z, X)), ((2, 2), (3, 1, Z, X)), ((2, 3), (0, 3, W, X)),
-            ((3, 0), (4, 0, X, Z)), ((3, 1), (5, 1, Z, X)), ((3, 2), (0, 0, M, Z)), ((3, 3), (2, 0, X, Z)),
-            ((4, 0), (1, 2, M, W)), ((4, 1), (5, 2, X, W)), ((4, 2), (3, 2, X, W)), ((4, 3), (2, 3, W, X)),
-            ((5, 0), (4, 3, W, X)), ((5, 1), (1, 1, Z, X)), ((5, 2), (0, 1, Z, X)), ((5, 3), (3, 3, W, X)),
-        ]);
-}
-
 #[derive(Debug)]
 enum Action {
     Move(i32),
@@ -203,11 +186,9 @@ mod tests {
         assert_eq!(part_one(input), 6032);
     }
 
-    // Comment out because our transition map is specific
-    // to the input layout.
-    // #[test]
-    // fn example_part_two() {
-    //     let input = include_str!("../example.txt");
-    //     assert_eq!(part_two(input), 5031);
-    // }
+    #[test]
+    fn example_part_two() {
+        let input = include_str!("../example.txt");
+        assert_eq!(part_two(input), 5031);
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 type Modules<'a> = HashMap<&'a str, Module<'a>>;
 type Network<'a> = VecDeque<(&'a str, &'a str, Pulse)>;
@@ -35,87 +35,111 @@ fn part_two(input:& str) -> u64
 {
     use num::integer::Integer;
 
-    // jt => 510 @ 3918 steps
-    // mh => 510 @ 4050 steps
-    // pz => 126 @ 3760 steps
-    // rn => 126 @ 3906 steps
-    // They all reset on the next step so the assumption
-    // is at some point they are all at the almost highest
-    // value together and the next step would put them all
-    // turned on. So, we get the LCM of that next step cycle.
-    // Thus: 3819, 4051, 3761 & 3907
-    // 233_283_622_908_263
-
-    let mut i = 1;
-    let mut needed = 4;
-    let mut registers = [0u64;4];
+    let (modules, _, _) = load(input);
+    counters(&modules)
+        .unwrap_or_else(|e| panic!("Can't tell when rx gets a low pulse: {e}"))
+        .into_iter()
+        .reduce(|acc, n| acc.lcm(&n))
+        .unwrap()
+}
 
-    let mut q = VecDeque::new();
+// rx gets a low pulse when every input to the conjunction in front of
+// it is high at once. Each of those inputs is the end of a separate
+// counter hanging off the broadcaster, which sends a high pulse once
+// per cycle and then resets, so they all line up at the LCM of the
+// cycle lengths.
+fn counters(modules: &Modules) -> Result<Vec<u64>, String>
+{
+    let feeding = modules.iter()
+        .filter(|(_, m)| m.outputs().contains(&"rx"))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    let hub = match feeding[..] {
+        [hub] => hub,
+        _ => return Err(format!("rx should have one input, not {feeding:?}")),
+    };
+    let Some(Module::Conjunction { inputs, .. }) = modules.get(hub) else {
+        return Err(format!("{hub} in front of rx isn't a conjunction"))
+    };
+    let Some(Module::Broadcaster { outputs: starts }) = modules.get("broadcaster") else {
+        return Err("There's no broadcaster".into())
+    };
+    if starts.len() != inputs.len() {
+        return Err(format!(
+            "{} counters from the broadcaster for {} inputs to {hub}", starts.len(), inputs.len()
+        ))
+    }
 
-    let (mut modules, _, _) = load(input);
-    while needed > 0 {
-        press(&mut modules, &mut q);
-
-        let rn = ["bx", "fx", "nx", "kn", "mv", "fk", "rv"].iter()
-            .enumerate()
-            .fold(0u64, |acc, (i, s)| {
-                let m = modules.get(s).unwrap();
-                acc | m.bit() << i
-            });
-        if rn == 126 {
-            registers[0] = i + 1;
-            needed -= 1;
-         }
-        
-        let pz = ["jp", "dx", "ph", "jc", "ct", "kd", "pp"].iter()
-            .enumerate()
-            .fold(0u64, |acc, (i, s)| {
-                let m = modules.get(s).unwrap();
-                acc | m.bit() << i
-            });
-        if pz == 126 {
-            registers[1] = i + 1;
-            needed -= 1;
-        }
-            
-        let jt = ["jq", "qt", "lj", "dt", "vp", "jm", "xk", "nk", "vk"].iter()
-            .enumerate()
-            .fold(0u64, |acc, (i, s)| {
-                let m = modules.get(s).unwrap();
-                acc | m.bit() << i
-            });
-        if jt == 510 {
-            registers[2] = i + 1;
-            needed -= 1;
-        }
+    let mut seen = HashSet::new();
+    starts.iter()
+        .map(|start| {
+            let counter = reachable(modules, start, hub);
+            if !counter.iter().all(|m| seen.insert(*m)) {
+                return Err(format!("The counter from {start} shares modules with another"))
+            }
 
-        let mh = ["nv", "th", "jf", "xm", "gv", "nr", "cj", "vh", "jh"].iter()
-            .enumerate()
-            .fold(0u64, |acc, (i, s)| {
-                let m = modules.get(s).unwrap();
-                acc | m.bit() << i
-            });
-        if mh == 510 {
-            registers[3] = i + 1;
-            needed -= 1;
-        }
-    
-        i += 1
+            let ends = inputs.keys().filter(|m| counter.contains(*m)).collect::<Vec<_>>();
+            match ends[..] {
+                [end] => cycle(modules, start, end, hub),
+                _ => Err(format!("The counter from {start} should feed {hub} once, not from {ends:?}")),
+            }
+        })
+        .collect()
+}
+
+/// Modules reachable from `start` without going through `hub`.
+fn reachable<'a>(modules: &Modules<'a>, start: &'a str, hub: &str) -> HashSet<&'a str>
+{
+    let mut seen = HashSet::from([start]);
+    let mut q = VecDeque::from([start]);
+    while let Some(name) = q.pop_front() {
+        modules.get(name).iter()
+            .flat_map(|m| m.outputs())
+            .filter(|m| *m != hub)
+            .for_each(|m| if seen.insert(m) { q.push_back(m) })
     }
 
-    registers.iter().cloned()
-        .reduce(|acc, n| acc.lcm(&n)).unwrap()
+    seen
+}
+
+/// Presses before the counter from `start` first sends a high pulse
+/// from `end` to `hub`, with the broadcaster driving it alone.
+fn cycle<'a>(modules: &Modules<'a>, start: &'a str, end: &str, hub: &str) -> Result<u64, String>
+{
+    // Longer than any counter in the puzzle inputs, which are 12 bits.
+    const LIMIT: u64 = 100_000;
+
+    let mut modules = modules.clone();
+    modules.insert("broadcaster", Module::Broadcaster { outputs: vec![start] });
+
+    let mut q = VecDeque::new();
+    let highs = (1..=LIMIT)
+        .filter(|_| press(&mut modules, &mut q, hub).contains(&(end, Pulse::High)))
+        .take(2)
+        .collect::<Vec<_>>();
+
+    match highs[..] {
+        [n, m] if m == 2 * n => Ok(n),
+        [n, m] => Err(format!("{end} went high after {n} and {m} presses, which isn't a cycle")),
+        _ => Err(format!("{end} didn't go high twice in {LIMIT} presses")),
+    }
 }
 
-fn press<'a>(modules: &mut Modules<'a>, q: &mut Network<'a>)
+/// Press the button and return the pulses `watch` got.
+fn press<'a>(modules: &mut Modules<'a>, q: &mut Network<'a>, watch: &str) -> Vec<(&'a str, Pulse)>
 {
+    let mut pulses = vec![];
+
     q.push_back(("button", "broadcaster", Pulse::Low));
     while let Some((src, dst, pulse)) = q.pop_front() {
+        if dst == watch { pulses.push((src, pulse)) }
         if let Some(module) = modules.get_mut(dst) {
             module.process(src, pulse).iter()
                 .for_each(|(m, pulse)| q.push_back((dst, m, *pulse)))
         }
     }
+
+    pulses
 }
 
 fn run(modules: &mut Modules) -> (u32, u32)
@@ -143,7 +167,7 @@ fn run(modules: &mut Modules) -> (u32, u32)
 }
 
 #[allow(clippy::manual_strip)]
-fn load(input: &str) -> (HashMap<&str, Module>, Vec<&str>, Vec<&str>)
+fn load(input: &str) -> (Modules<'_>, Vec<&str>, Vec<&str>)
 {
     use Module::*;
 
@@ -193,7 +217,7 @@ enum Module<'a> {
     Broadcaster { outputs: Vec<&'a str> },
 }
 impl<'a> Module<'a> {
-    fn outputs(&self) -> Vec<&str>
+    fn outputs(&self) -> Vec<&'a str>
     {
         use Module::*;
 
@@ -249,19 +273,6 @@ impl<'a> Module<'a> {
         }
     }
 
-    fn bit(&self) -> u64
-    {
-        use Module::*;
-
-        match self {
-            FlipFlop { on, .. } => *on as u64,
-            Broadcaster { .. } => 1,
-            Conjunction { inputs, .. } => {
-                let on = inputs.values().any(|p| *p == Pulse::Low);
-                on as u64
-            },
-        }
-    }
 }
 
 
@@ -283,6 +294,52 @@ mod tests {
         assert_eq!(part_two(input), 233_283_622_908_263);
     }
 
+    // Two counters off the broadcaster, resetting after 3 and 5 presses.
+    const COUNTERS: &str = "\
+broadcaster -> a0, b0
+%a0 -> a1, ca
+%a1 -> ca
+&ca -> a0, ea
+&ea -> hub
+%b0 -> b1, cb
+%b1 -> b2
+%b2 -> cb
+&cb -> b0, b1, eb
+&eb -> hub
+&hub -> rx";
+
+    #[test]
+    fn counters_part_two()
+    {
+        let (modules, _, _) = load(COUNTERS);
+        let mut cycles = counters(&modules).unwrap();
+        cycles.sort();
+        assert_eq!(cycles, [3, 5]);
+        assert_eq!(part_two(COUNTERS), 15);
+
+        let (mut modules, _, _) = load(COUNTERS);
+        let mut q = VecDeque::new();
+        let presses = (1..)
+            .find(|_| press(&mut modules, &mut q, "rx").contains(&("hub", Pulse::Low)))
+            .unwrap();
+        assert_eq!(presses, 15);
+    }
+
+    #[test]
+    fn counters_errors()
+    {
+        let (modules, _, _) = load(include_str!("../example2.txt"));
+        assert_eq!(counters(&modules), Err("rx should have one input, not []".into()));
+
+        let input = COUNTERS.replace("&hub", "%hub");
+        let (modules, _, _) = load(&input);
+        assert_eq!(counters(&modules), Err("hub in front of rx isn't a conjunction".into()));
+
+        let input = COUNTERS.replace("%b1 -> b2", "%b1 -> b2, a1");
+        let (modules, _, _) = load(&input);
+        assert_eq!(counters(&modules), Err("The counter from b0 shares modules with another".into()));
+    }
+
     #[test]
     fn example1_part_one()
     {
@@ -1,3 +1,5 @@
+use regvm::chrono::{self, Inst};
+
 fn main()
 {
     use std::time::Instant;
@@ -15,8 +17,8 @@ fn main()
 
 fn part_one(input: &str) -> String
 {
-    let (a, b, c, program) = load(input);
-    execute([a, b, c], &program).iter()
+    let (regs, _, program) = load(input);
+    execute(regs, &program).iter()
         .map(|n| n.to_string())
         .collect::<Vec<_>>()
         .join(",")
@@ -26,7 +28,7 @@ fn part_two(input: &str) -> u64
 {
     use pathfinding::directed::dfs::dfs;
 
-    let (_, b, c, p) = load(input);
+    let ([_, b, c], p, program) = load(input);
 
     let mut g = p.clone();
     g.reverse();
@@ -38,14 +40,14 @@ fn part_two(input: &str) -> u64
     // fails.
     let result = dfs(
         (0, vec![]),
-        |(n, _)| neighbors(*n, b, c, &p, &g),
+        |(n, _)| neighbors(*n, b, c, &program, &g),
         |(_, v)| v.len() == p.len()
     ).unwrap();
 
     result.last().unwrap().0
 }
 
-fn neighbors(n: u64, b: u64, c: u64, p: &[u64], g: &[u64]) -> Vec<(u64, Vec<u64>)>
+fn neighbors(n: u64, b: u64, c: u64, p: &[Inst], g: &[u64]) -> Vec<(u64, Vec<u64>)>
 {
     // It's a 3 bit computer. Each loop produces one output and
     // them the value in the A register loses 3 bits (divided by 8).
@@ -61,7 +63,7 @@ fn neighbors(n: u64, b: u64, c: u64, p: &[u64], g: &[u64]) -> Vec<(u64, Vec<u64>
             (a, execute([a, b, c], p))
         })
         .filter(|(_, v)| {
-            v.len() <= p.len() &&
+            v.len() <= g.len() &&
             v.iter()
                 .rev()
                 .zip(g)
@@ -70,96 +72,21 @@ fn neighbors(n: u64, b: u64, c: u64, p: &[u64], g: &[u64]) -> Vec<(u64, Vec<u64>
         .collect()
 }
 
-fn execute(mut regs: [u64; 3], program: &[u64]) -> Vec<u64>
-{
-    let mut ip = 0;
-    let mut stdout = Vec::with_capacity(16);
-    while ip < program.len() {
-        let (nx, out) = process(ip, &mut regs, program);
-        if let Some(n) = out { stdout.push(n) }
-        ip = nx
-    }
-
-    stdout
-}
-
-fn process(mut ip: usize, regs: &mut[u64; 3], program: &[u64]) -> (usize, Option<u64>)
+fn execute(regs: [u64; 3], program: &[Inst]) -> Vec<u64>
 {
-    let mut out = None;
-
-    match program[ip] {
-        0 => {  // adv
-            ip += 1;
-            let d = 2_u64.pow(combo(program[ip], regs) as u32);
-            regs[0] /= d;
-        },
-        1 => {  // bxl
-            ip += 1;
-            regs[1] ^= program[ip];
-        },
-        2 => {  // bst
-            ip += 1;
-            regs[1] = combo(program[ip], regs) % 8;
-        },
-        3 => {  // jnz
-            ip += 1;
-            if regs[0] != 0 { 
-                ip = program[ip] as usize;
-                return (ip, None);
-            }
-        },
-        4 => {  // bxc
-            ip += 1;
-            regs[1] ^= regs[2];
-        },
-        5 => {  // out
-            ip += 1;
-            out = Some(combo(program[ip], regs) % 8);
-        },
-        6 => {  // bdv
-            ip += 1;
-            let d = 2_u64.pow(combo(program[ip], regs) as u32);
-            regs[1] = regs[0] / d;
-        },
-        7 => {  // cdv
-            ip += 1;
-            let d = 2_u64.pow(combo(program[ip], regs) as u32);
-            regs[2] = regs[0] / d;
-        },
-        _ => unreachable!()
-    }
-
-    (ip + 1, out)
+    let mut m = chrono::machine(regs, program.to_vec());
+    m.run();
+    m.drain()
 }
 
-fn combo(n: u64, regs: &[u64; 3]) -> u64
+fn load(input: &str) -> ([u64; 3], Vec<u64>, Vec<Inst>)
 {
-    match n {
-        0..=3 => n,
-        4..=6 => regs[n as usize - 4],
-        _ => unreachable!()
-    }
-}
+    let (regs, words) = chrono::parse(input).unwrap();
+    let program = chrono::decode(&words).unwrap();
 
-fn load(input: &str) -> (u64, u64, u64, Vec<u64>)
-{
-    let (s1, s2) = input.split_once("\n\n").unwrap();
-    let mut line = s1.lines();
-    let s = line.next().unwrap();
-    let a = s[12..].parse::<u64>().unwrap();
-    let s = line.next().unwrap();
-    let b = s[12..].parse::<u64>().unwrap();
-    let s = line.next().unwrap();
-    let c = s[12..].parse::<u64>().unwrap();
-
-    let p = s2[9..].split(',')
-        .flat_map(|s| s.parse::<u64>())
-        .collect::<Vec<_>>();
-
-    (a, b, c, p)
+    (regs, words, program)
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,6 @@
 #![allow(clippy::manual_strip)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 type Gate<'a>  = (&'a str, &'a str, char);
 type Gates<'a> = HashMap<&'a str, Gate<'a>>;
@@ -30,38 +30,183 @@ fn part_one(input: &str) -> u64
 fn part_two(input: &str) -> String
 {
     let (wires, gates) = load(input);
+    swaps(&wires, &gates)
+}
 
-    let mut x = 0_u64;
-    let mut y = 0_u64;
-    wires.iter()
-        .filter(|(_, &n)| n == 1)
-        .flat_map(|(w, _)| w[1..].parse::<u64>().map(|i| (w, i)))
-        .for_each(|(w, i)| {
-            match w.chars().next() {
-                Some('x') => x |= 1 << i,
-                Some('y') => y |= 1 << i,
-                _ => unreachable!()
-            }
-        });
+// The gates should make a ripple carry adder, which for each bit i is
+//
+//     s = x ^ y
+//     z = s ^ carry in
+//     carry out = (x & y) | (s & carry in)
+//
+// with bit 0 a half adder (z00 = x00 ^ y00, carry out = x00 & y00) and
+// the last carry out going straight to the top z bit. Following the
+// carry up bit by bit, any wire that isn't where that shape puts it is
+// a suspect, and the answer is the smallest set of swaps between
+// suspects that gives an adder which adds.
+fn swaps(wires: &Wires, gates: &Gates) -> String
+{
+    let suspects = suspects(gates);
+    let mut wires = repair(wires, gates, &suspects)
+        .unwrap_or_else(|| panic!("No swaps between {suspects:?} make an adder"));
+
+    wires.sort();
+    wires.join(",")
+}
+
+fn suspects<'a>(gates: &Gates<'a>) -> Vec<&'a str>
+{
+    let by_inputs = gates.iter()
+        .flat_map(|(out, (a, b, op))| [((*a, *b, *op), *out), ((*b, *a, *op), *out)])
+        .collect::<HashMap<_, _>>();
+    let find = |a: &str, b: &str, op: char| by_inputs.get(&(a, b, op)).copied();
+    let wire = |c: char, i: usize| gates.keys().chain(by_inputs.keys().map(|k| &k.0))
+        .find(|w| **w == format!("{c}{i:02}"))
+        .copied();
+
+    let bits = gates.keys().filter(|w| w.starts_with('z')).count() - 1;
+    let mut wrong = BTreeSet::new();
+
+    // Bit 0 is a half adder.
+    let (Some(x), Some(y)) = (wire('x', 0), wire('y', 0)) else { return vec![] };
+    if let Some(z) = find(x, y, '^').filter(|z| *z != "z00") {
+        wrong.extend([z, "z00"]);
+    }
+    let mut carry = find(x, y, '&');
+
+    for i in 1..bits {
+        let (Some(x), Some(y), Some(z), Some(c)) = (wire('x', i), wire('y', i), wire('z', i), carry)
+            else { break };
+        let (Some(mut s), Some(a)) = (find(x, y, '^'), find(x, y, '&')) else { break };
+        let mut c = c;
+
+        // z = s ^ carry in. If there's no such gate, whatever makes z
+        // shows which of s and the carry is in the wrong place.
+        match find(s, c, '^') {
+            Some(out) if out != z => { wrong.extend([out, z]); },
+            Some(_) => {},
+            None => match gates.get(z) {
+                Some((p, q, '^')) => {
+                    [s, c].iter().filter(|w| *w != p && *w != q).for_each(|w| { wrong.insert(*w); });
+                    [*p, *q].iter().filter(|w| **w != s && **w != c).for_each(|w| { wrong.insert(*w); });
+                    if c == *p { s = q } else if c == *q { s = p }
+                    else if s == *p { c = q } else if s == *q { c = p }
+                },
+                _ => { wrong.insert(z); },
+            },
+        }
+
+        // carry out = (x & y) | (s & carry in)
+        let Some(t) = find(s, c, '&') else { break };
+        carry = match find(a, t, '|') {
+            Some(out) => Some(out),
+            None => gates.iter()
+                .find(|(_, (p, q, op))| *op == '|' && [a, t].iter().any(|w| w == p || w == q))
+                .map(|(out, (p, q, _))| {
+                    [a, t].iter().filter(|w| *w != p && *w != q).for_each(|w| { wrong.insert(*w); });
+                    [*p, *q].iter().filter(|w| **w != a && **w != t).for_each(|w| { wrong.insert(*w); });
+                    *out
+                }),
+        };
+    }
+
+    // The last carry out is the top bit.
+    if let (Some(c), Some(z)) = (carry, wire('z', bits)) {
+        if c != z { wrong.extend([c, z]); }
+    }
+
+    wrong.into_iter().collect()
+}
+
+/// The wires in the fewest swaps between suspects that make the
+/// gates add.
+fn repair<'a>(wires: &Wires<'a>, gates: &Gates<'a>, suspects: &[&'a str]) -> Option<Vec<&'a str>>
+{
+    (0..=suspects.len() / 2)
+        .flat_map(|k| pairings(suspects, k))
+        .find(|pairs| {
+            let mut g = gates.clone();
+            pairs.iter().for_each(|(a, b)| {
+                g.insert(a, gates[b]);
+                g.insert(b, gates[a]);
+            });
+            adds(wires, &g)
+        })
+        .map(|pairs| pairs.iter().flat_map(|(a, b)| [*a, *b]).collect())
+}
+
+/// Every way of picking k disjoint pairs of wires.
+fn pairings<'a>(wires: &[&'a str], k: usize) -> Vec<Vec<(&'a str, &'a str)>>
+{
+    if k == 0 { return vec![vec![]] }
+
+    // The first wire in a pair comes before any in the later pairs, so
+    // each set of pairs turns up once.
+    (0..wires.len())
+        .flat_map(|i| (i + 1..wires.len()).map(move |j| (i, j)))
+        .flat_map(|(i, j)| {
+            let rest = wires[i + 1..].iter()
+                .filter(|w| **w != wires[j])
+                .copied()
+                .collect::<Vec<_>>();
+            pairings(&rest, k - 1).into_iter().map(move |mut pairs| {
+                pairs.push((wires[i], wires[j]));
+                pairs
+            })
+        })
+        .collect()
+}
+
+/// Check z = x + y for each bit on its own and in pairs, carries all
+/// the way up, and a batch of random numbers.
+fn adds(wires: &Wires, gates: &Gates) -> bool
+{
+    use rand::Rng;
+
+    if !is_acyclic(gates) { return false }
+
+    let bits = wires.keys().filter(|w| w.starts_with('x')).count();
+    let mask = (1_u64 << bits) - 1;
+    let mut rng = rand::thread_rng();
+
+    let mut tests = (0..bits)
+        .flat_map(|i| [(1 << i, 0), (0, 1 << i), (1 << i, 1 << i)])
+        .collect::<Vec<_>>();
+    tests.extend([(0, 0), (mask, 1), (1, mask), (mask, mask)]);
+    tests.extend((0..100).map(|_| (rng.gen::<u64>() & mask, rng.gen::<u64>() & mask)));
+
+    tests.into_iter().all(|(x, y)| evaluate(&inputs(wires, x, y), gates) == x + y)
+}
+
+fn inputs<'a>(wires: &Wires<'a>, x: u64, y: u64) -> Wires<'a>
+{
+    wires.keys()
+        .map(|w| {
+            let n = if w.starts_with('x') { x } else { y };
+            let i = w[1..].parse::<u64>().unwrap();
+            (*w, n >> i & 1)
+        })
+        .collect()
+}
+
+fn is_acyclic(gates: &Gates) -> bool
+{
+    // Depth first, with false marking wires still being visited.
+    fn visit<'a>(w: &'a str, gates: &Gates<'a>, done: &mut HashMap<&'a str, bool>) -> bool
+    {
+        if let Some(finished) = done.get(w) {
+            return *finished
+        }
+        let Some((a, b, _)) = gates.get(w) else { return true };
+
+        done.insert(w, false);
+        let ok = visit(a, gates, done) && visit(b, gates, done);
+        done.insert(w, true);
+        ok
+    }
 
-    // Solved manually after looking up how binary adders are implemented
-    // and printing out how the z bits were directly being set and noticing
-    // 4 were not like the others. However the last was due to being the last
-    // and printing out the values in bites showed 31 was off which led to 
-    // the "wrk" / "jrs" swap.
-    let mut g = gates.clone();
-    g.insert("z15", *gates.get("fph").unwrap());
-    g.insert("fph", *gates.get("z15").unwrap());
-    g.insert("z21", *gates.get("gds").unwrap());
-    g.insert("gds", *gates.get("z21").unwrap());
-    g.insert("jrs", *gates.get("wrk").unwrap());
-    g.insert("wrk", *gates.get("jrs").unwrap());
-    g.insert("z34", *gates.get("cqk").unwrap());
-    g.insert("cqk", *gates.get("z34").unwrap());
-    let z = evaluate(&wires, &g);
-    assert!(z == x + y);
-
-    "cqk,fph,gds,jrs,wrk,z15,z21,z34".into()
+    let mut done = HashMap::new();
+    gates.keys().all(|w| visit(w, gates, &mut done))
 }
 
 fn evaluate(wires: &Wires, gates: &Gates) -> u64
@@ -95,7 +240,7 @@ fn solve<'a>(w: &'a str, wires: &mut Wires<'a>, gates: &Gates<'a>) -> u64
     }
 }
 
-fn load(input: &str) -> (Wires, Gates)
+fn load(input: &str) -> (Wires<'_>, Gates<'_>)
 {
     let (s1, s2) = input.split_once("\n\n").unwrap();
     let wires = s1.lines()
@@ -143,6 +288,58 @@ mod tests {
         assert_eq!(part_two(input), "cqk,fph,gds,jrs,wrk,z15,z21,z34");
     }
 
+    #[test]
+    fn repairs_adder()
+    {
+        // An eight bit adder with z03 / c03 and s06 / a06 swapped.
+        let mut wires = String::new();
+        let mut gates = vec![
+            "x00 XOR y00 -> z00".to_string(),
+            "x00 AND y00 -> c00".to_string(),
+        ];
+        (0..8).for_each(|i| wires += &format!("x{i:02}: 0\ny{i:02}: 0\n"));
+        (1..8).for_each(|i| {
+            let c = if i == 7 { "z08".to_string() } else { format!("c{i:02}") };
+            gates.extend([
+                format!("x{i:02} XOR y{i:02} -> s{i:02}"),
+                format!("s{i:02} XOR c{:02} -> z{i:02}", i - 1),
+                format!("x{i:02} AND y{i:02} -> a{i:02}"),
+                format!("s{i:02} AND c{:02} -> t{i:02}", i - 1),
+                format!("a{i:02} OR t{i:02} -> {c}"),
+            ])
+        });
+
+        let input = format!("{wires}\n{}", gates.join("\n"));
+        let (wires, gates) = load(&input);
+        assert!(suspects(&gates).is_empty());
+        assert!(adds(&wires, &gates));
+        assert_eq!(swaps(&wires, &gates), "");
+
+        let mut swapped = gates.clone();
+        [("z03", "c03"), ("s06", "a06")].iter().for_each(|(a, b)| {
+            swapped.insert(a, gates[b]);
+            swapped.insert(b, gates[a]);
+        });
+        assert!(!adds(&wires, &swapped));
+        assert_eq!(suspects(&swapped), ["a06", "c03", "s06", "z03"]);
+        assert_eq!(swaps(&wires, &swapped), "a06,c03,s06,z03");
+
+        // Two gates of the same kind
+        let mut swapped = gates.clone();
+        swapped.insert("s05", gates["s06"]);
+        swapped.insert("s06", gates["s05"]);
+        assert!(!adds(&wires, &swapped));
+        assert_eq!(suspects(&swapped), ["s05", "s06"]);
+        assert_eq!(swaps(&wires, &swapped), "s05,s06");
+
+        let mut swapped = gates.clone();
+        swapped.insert("z02", gates["z05"]);
+        swapped.insert("z05", gates["z02"]);
+        swapped.insert("c03", gates["c06"]);
+        swapped.insert("c06", gates["c03"]);
+        assert_eq!(swaps(&wires, &swapped), "c03,c06,z02,z05");
+    }
+
     #[test]
     fn example_part_one()
     {
@@ -0,0 +1,202 @@
+//! Reading the block letters several puzzles draw as their answer.
+//! The height of the lit rows picks the font: six rows for the small
+//! one, ten for the large one.
+
+use std::error::Error;
+use std::fmt;
+
+mod font;
+
+#[cfg(test)]
+mod test;
+
+/// A grid of lit and dark pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    lit: Vec<bool>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Bitmap { width, height, lit: vec![false; width * height] }
+    }
+
+    /// A bitmap just big enough for the points, which can start
+    /// anywhere.
+    pub fn from_points<I>(points: I) -> Self
+        where I: IntoIterator<Item = (i64, i64)>
+    {
+        let points = points.into_iter().collect::<Vec<_>>();
+        let (Some(x0), Some(y0)) = (
+            points.iter().map(|p| p.0).min(),
+            points.iter().map(|p| p.1).min(),
+        ) else {
+            return Bitmap::new(0, 0)
+        };
+        let x1 = points.iter().map(|p| p.0).max().unwrap();
+        let y1 = points.iter().map(|p| p.1).max().unwrap();
+
+        let mut bitmap = Bitmap::new((x1 - x0 + 1) as usize, (y1 - y0 + 1) as usize);
+        for (x, y) in points {
+            bitmap.set((x - x0) as usize, (y - y0) as usize, true);
+        }
+        bitmap
+    }
+
+    /// Rows of `#` for lit pixels; anything else is dark.
+    pub fn from_text(text: &str) -> Self {
+        let rows = text.lines().collect::<Vec<_>>();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+
+        let mut bitmap = Bitmap::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                bitmap.set(x, y, c == '#');
+            }
+        }
+        bitmap
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.lit[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, lit: bool) {
+        assert!(x < self.width && y < self.height, "({x}, {y}) is outside the bitmap");
+        self.lit[y * self.width + x] = lit
+    }
+}
+
+impl fmt::Display for Bitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            let row = (0..self.width)
+                .map(|x| if self.get(x, y) { '#' } else { '.' })
+                .collect::<String>();
+            writeln!(f, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Letters that didn't match the font, drawn as they appeared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownGlyphs {
+    /// What was read, with `?` for each unknown glyph.
+    pub text: String,
+    pub glyphs: Vec<Bitmap>,
+}
+
+impl fmt::Display for UnknownGlyphs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} unknown glyph(s) reading {:?}", self.glyphs.len(), self.text)?;
+        for glyph in &self.glyphs {
+            write!(f, "\n\n{glyph}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for UnknownGlyphs {}
+
+/// A glyph as a bit mask per column, top row in bit 0, without the
+/// blank columns either side.
+struct Glyph {
+    c: char,
+    columns: Vec<u16>,
+}
+
+fn glyphs(font: &[(char, &str)]) -> Vec<Glyph> {
+    let mut glyphs = font.iter()
+        .map(|(c, rows)| {
+            let rows = rows.split(' ').map(|r| r.as_bytes()).collect::<Vec<_>>();
+            let columns = (0..rows[0].len())
+                .map(|x| column(rows.len(), |y| rows[y][x] == b'#'))
+                .filter(|mask| *mask != 0)
+                .collect();
+            Glyph { c: *c, columns }
+        })
+        .collect::<Vec<_>>();
+
+    // Wider glyphs first, so a narrow one can't match part of them.
+    glyphs.sort_by_key(|g| std::cmp::Reverse(g.columns.len()));
+    glyphs
+}
+
+fn column(height: usize, lit: impl Fn(usize) -> bool) -> u16 {
+    (0..height).filter(|y| lit(*y)).fold(0, |mask, y| mask | 1 << y)
+}
+
+/// Read the letters in a bitmap.
+pub fn read(bitmap: &Bitmap) -> Result<String, UnknownGlyphs> {
+    let lit_rows = (0..bitmap.height)
+        .filter(|y| (0..bitmap.width).any(|x| bitmap.get(x, *y)))
+        .collect::<Vec<_>>();
+    let (top, height) = match (lit_rows.first(), lit_rows.last()) {
+        (Some(top), Some(bottom)) => (*top, bottom - top + 1),
+        _ => return Ok(String::new()),
+    };
+
+    let font = match height {
+        6 => glyphs(font::SMALL),
+        10 => glyphs(font::LARGE),
+        _ => return Err(UnknownGlyphs { text: "?".to_string(), glyphs: vec![bitmap.clone()] }),
+    };
+    let columns = (0..bitmap.width)
+        .map(|x| column(height, |y| bitmap.get(x, top + y)))
+        .collect::<Vec<_>>();
+
+    let mut text = String::new();
+    let mut unknown = vec![];
+    let mut x = 0;
+    while x < columns.len() {
+        if columns[x] == 0 {
+            x += 1;
+            continue
+        }
+
+        match font.iter().find(|g| columns[x..].starts_with(&g.columns)) {
+            Some(glyph) => {
+                text.push(glyph.c);
+                x += glyph.columns.len();
+            },
+            None => {
+                let end = (x..columns.len()).find(|x| columns[*x] == 0).unwrap_or(columns.len());
+                unknown.push(crop(bitmap, x..end, top..top + height));
+                text.push('?');
+                x = end;
+            },
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(UnknownGlyphs { text, glyphs: unknown })
+    }
+}
+
+/// Read the letters drawn with `#` in rows of text.
+pub fn read_text(text: &str) -> Result<String, UnknownGlyphs> {
+    read(&Bitmap::from_text(text))
+}
+
+fn crop(bitmap: &Bitmap, xs: std::ops::Range<usize>, ys: std::ops::Range<usize>) -> Bitmap {
+    let mut glyph = Bitmap::new(xs.len(), ys.len());
+    for (i, x) in xs.enumerate() {
+        for (j, y) in ys.clone().enumerate() {
+            glyph.set(i, j, bitmap.get(x, y));
+        }
+    }
+    glyph
+}
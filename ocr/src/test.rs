@@ -0,0 +1,80 @@
+use super::*;
+
+/// Draw `text` in a font, letters `gap` columns apart.
+fn draw(font: &[(char, &str)], text: &str, gap: usize) -> String {
+    let glyphs = text.chars()
+        .map(|c| font.iter().find(|g| g.0 == c).unwrap().1.split(' ').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    (0..glyphs[0].len())
+        .map(|y| glyphs.iter()
+            .map(|g| g[y].to_string())
+            .collect::<Vec<_>>()
+            .join(&".".repeat(gap))
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn small() {
+    let text = "ABCEFGHIJKLOPRSUYZ";
+    assert_eq!(read_text(&draw(font::SMALL, text, 1)), Ok(text.to_string()));
+
+    // Y fills the whole cell, so there's no gap before the J.
+    let image = "\
+####...##.#..#.###..#..#..##..###..#....#...#..##.
+...#....#.#..#.#..#.#.#..#..#.#..#.#....#...#...#.
+..#.....#.####.#..#.##...#....#..#.#.....#.#....#.
+.#......#.#..#.###..#.#..#....###..#......#.....#.
+#....#..#.#..#.#.#..#.#..#..#.#....#......#..#..#.
+####..##..#..#.#..#.#..#..##..#....####...#...##..";
+    assert_eq!(read_text(image).unwrap(), "ZJHRKCPLYJ");
+}
+
+#[test]
+fn large() {
+    let text = "ABCEFGHJKLNPRXZ";
+    assert_eq!(read_text(&draw(font::LARGE, text, 2)), Ok(text.to_string()));
+
+    // Blank rows and columns around the letters don't matter.
+    let letters = draw(font::LARGE, "HJ", 2).lines()
+        .map(|row| format!("...{row}..."))
+        .collect::<Vec<_>>();
+    let image = format!("\n\n{}\n\n", letters.join("\n"));
+    assert_eq!(read_text(&image).unwrap(), "HJ");
+}
+
+#[test]
+fn points() {
+    let image = draw(font::SMALL, "OK", 1);
+    let points = Bitmap::from_text(&image).lit.iter()
+        .enumerate()
+        .filter(|(_, lit)| **lit)
+        .map(|(i, _)| (i as i64 % 9 - 20, i as i64 / 9 - 3))
+        .collect::<Vec<_>>();
+
+    let bitmap = Bitmap::from_points(points);
+    assert_eq!((bitmap.width(), bitmap.height()), (9, 6));
+    assert_eq!(read(&bitmap).unwrap(), "OK");
+}
+
+#[test]
+fn unknown() {
+    let t = "###\n.#.\n.#.\n.#.\n.#.\n.#.";
+    let image = draw(font::SMALL, "HI", 1)
+        .lines()
+        .zip(t.lines())
+        .map(|(a, b)| format!("{a}..{b}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let err = read_text(&image).unwrap_err();
+    assert_eq!(err.text, "HI?");
+    assert_eq!(err.glyphs, [Bitmap::from_text(t)]);
+    assert!(err.to_string().starts_with("1 unknown glyph(s) reading \"HI?\"\n\n###\n.#.\n"));
+
+    // No font is eight rows high.
+    let err = read_text("#\n#\n#\n#\n#\n#\n#\n#").unwrap_err();
+    assert_eq!(err.text, "?");
+}
@@ -0,0 +1,67 @@
+//! 2021 day 24: registers `w`, `x`, `y` and `z`, with `inp` reading
+//! the next input value.
+
+use crate::{operand, parse_lines, register, Flow, Machine, Operand, ParseError};
+
+const REGISTERS: &str = "wxyz";
+
+type Value = Operand<i64>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Inp(usize),
+    Add(usize, Value),
+    Mul(usize, Value),
+    Div(usize, Value),
+    Mod(usize, Value),
+    Eql(usize, Value),
+}
+
+impl crate::Inst for Inst {
+    type Word = i64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        use Inst::*;
+
+        let regs = m.regs_mut();
+        match *self {
+            Inp(r) => match m.read_input() {
+                Some(v) => m.set_reg(r, v),
+                None => return Flow::Wait,
+            },
+            Add(r, b) => regs[r] += b.get(regs),
+            Mul(r, b) => regs[r] *= b.get(regs),
+            Div(r, b) => regs[r] /= b.get(regs),
+            Mod(r, b) => regs[r] %= b.get(regs),
+            Eql(r, b) => regs[r] = (regs[r] == b.get(regs)) as i64,
+        }
+
+        Flow::Next
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Inst>, ParseError> {
+    parse_lines(input, |tokens| {
+        let reg = |s| register(s, REGISTERS);
+        let val = |s| operand(s, REGISTERS);
+        let inst = match tokens {
+            ["inp", a] => Inst::Inp(reg(a)?),
+            ["add", a, b] => Inst::Add(reg(a)?, val(b)?),
+            ["mul", a, b] => Inst::Mul(reg(a)?, val(b)?),
+            ["div", a, b] => Inst::Div(reg(a)?, val(b)?),
+            ["mod", a, b] => Inst::Mod(reg(a)?, val(b)?),
+            ["eql", a, b] => Inst::Eql(reg(a)?, val(b)?),
+            _ => return None,
+        };
+        Some(inst)
+    })
+}
+
+pub fn machine(program: Vec<Inst>) -> Machine<Inst> {
+    Machine::new(program, REGISTERS.len())
+}
+
+/// Register index of a name.
+pub fn reg(name: char) -> usize {
+    REGISTERS.find(name).expect("registers are w, x, y and z")
+}
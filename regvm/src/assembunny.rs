@@ -0,0 +1,188 @@
+//! 2016 days 12, 23 and 25: registers `a` to `d`. `tgl` rewrites the
+//! program, which can leave instructions that make no sense (a copy
+//! into a number, say); those are skipped.
+//!
+//! Adding and multiplying are done with `inc`/`dec`/`jnz` loops, so
+//! `optimize` replaces the first instruction of each loop with an
+//! `Add` or `Mul` that does the whole loop in one step. The rest of
+//! the loop is left in place, so jumps into it and addresses still
+//! work, and `tgl` in an optimized program puts the original
+//! instructions back before it toggles anything.
+
+use crate::{operand, parse_lines, Flow, Machine, Operand, ParseError};
+
+const REGISTERS: &str = "abcd";
+
+type Value = Operand<i64>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Cpy(Value, Value),
+    Inc(Value),
+    Dec(Value),
+    Jnz(Value, Value),
+    Tgl(Value),
+    Out(Value),
+    /// `inc x`, `dec y`, `jnz y -2` or with the first two swapped:
+    /// x += y and y = 0.
+    Add { x: usize, y: usize, inc_first: bool },
+    /// `cpy src tmp`, an `Add` of tmp into x, `dec count` and
+    /// `jnz count -5`: x += src * count, tmp = 0 and count = 0.
+    Mul { x: usize, src: Value, tmp: usize, count: usize },
+    /// `tgl` in an optimized program, which is optimized again after
+    /// the toggle.
+    Retgl(Value),
+}
+
+impl Inst {
+    /// The instruction `tgl` turns this one into.
+    pub fn toggle(self) -> Self {
+        use Inst::*;
+
+        match self {
+            Inc(x) => Dec(x),
+            Dec(x) | Tgl(x) | Out(x) => Inc(x),
+            Jnz(x, y) => Cpy(x, y),
+            Cpy(x, y) => Jnz(x, y),
+            Add { .. } | Mul { .. } | Retgl(_) => self.source().toggle(),
+        }
+    }
+
+    /// The instruction an `Add` or `Mul` replaced.
+    pub fn source(self) -> Self {
+        use Inst::*;
+
+        match self {
+            Add { x, inc_first: true, .. } => Inc(Operand::Reg(x)),
+            Add { y, inc_first: false, .. } => Dec(Operand::Reg(y)),
+            Mul { src, tmp, .. } => Cpy(src, Operand::Reg(tmp)),
+            Retgl(x) => Tgl(x),
+            inst => inst,
+        }
+    }
+
+}
+
+impl crate::Inst for Inst {
+    type Word = i64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        use Inst::*;
+
+        let regs = m.regs_mut();
+        match *self {
+            // The loops only end if their counters start positive, so
+            // anything else runs the original instructions.
+            Add { x, y, .. } if regs[y] > 0 => {
+                regs[x] += regs[y];
+                regs[y] = 0;
+                return Flow::Jump(3)
+            },
+            Mul { x, src, tmp, count } if src.get(regs) > 0 && regs[count] > 0 => {
+                regs[x] += src.get(regs) * regs[count];
+                regs[tmp] = 0;
+                regs[count] = 0;
+                return Flow::Jump(6)
+            },
+            Add { .. } | Mul { .. } => return self.source().exec(m),
+            Cpy(x, Operand::Reg(r)) => regs[r] = x.get(regs),
+            Inc(Operand::Reg(r)) => regs[r] += 1,
+            Dec(Operand::Reg(r)) => regs[r] -= 1,
+            Jnz(x, y) if x.get(regs) != 0 => return Flow::Jump(y.get(regs)),
+            Tgl(x) | Retgl(x) => {
+                let target = m.ip() as i64 + x.get(m.regs());
+                if let Ok(t) = usize::try_from(target) {
+                    toggle(m.program_mut(), t, matches!(self, Retgl(_)))
+                }
+            },
+            Out(x) => {
+                let v = x.get(regs);
+                m.write_output(v)
+            },
+            _ => {},
+        }
+
+        Flow::Next
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Inst>, ParseError> {
+    parse_lines(input, |tokens| {
+        let val = |s| operand(s, REGISTERS);
+        let inst = match tokens {
+            ["cpy", x, y] => Inst::Cpy(val(x)?, val(y)?),
+            ["inc", x] => Inst::Inc(val(x)?),
+            ["dec", x] => Inst::Dec(val(x)?),
+            ["jnz", x, y] => Inst::Jnz(val(x)?, val(y)?),
+            ["tgl", x] => Inst::Tgl(val(x)?),
+            ["out", x] => Inst::Out(val(x)?),
+            _ => return None,
+        };
+        Some(inst)
+    })
+}
+
+/// Toggle the instruction at `t`. An optimized program goes back to
+/// its original instructions first and is optimized again after, as
+/// the toggle can break a loop or make a new one.
+fn toggle(program: &mut [Inst], t: usize, optimized: bool) {
+    if t >= program.len() {
+        return
+    }
+
+    if optimized {
+        program.iter_mut().for_each(|inst| *inst = inst.source());
+        program[t] = program[t].toggle();
+        let optimized = optimize(program);
+        program.copy_from_slice(&optimized);
+    } else {
+        program[t] = program[t].toggle();
+    }
+}
+
+/// Replace the first instruction of each addition and multiplication
+/// loop with an `Add` or `Mul`.
+pub fn optimize(program: &[Inst]) -> Vec<Inst> {
+    let source = program.iter().map(|inst| inst.source()).collect::<Vec<_>>();
+    (0..source.len())
+        .map(|i| match source[i] {
+            Inst::Tgl(x) => Inst::Retgl(x),
+            inst => mul(&source[i..]).or_else(|| add(&source[i..])).unwrap_or(inst),
+        })
+        .collect()
+}
+
+fn add(code: &[Inst]) -> Option<Inst> {
+    use Inst::*;
+    use Operand::*;
+
+    let (x, y, j, inc_first) = match code {
+        [Inc(Reg(x)), Dec(Reg(y)), Jnz(Reg(j), Imm(-2)), ..] => (*x, *y, *j, true),
+        [Dec(Reg(y)), Inc(Reg(x)), Jnz(Reg(j), Imm(-2)), ..] => (*x, *y, *j, false),
+        _ => return None,
+    };
+
+    (x != y && j == y).then_some(Add { x, y, inc_first })
+}
+
+fn mul(code: &[Inst]) -> Option<Inst> {
+    use Inst::*;
+    use Operand::*;
+
+    let (src, tmp, count, j) = match code {
+        [Cpy(src, Reg(tmp)), _, _, _, Dec(Reg(count)), Jnz(Reg(j), Imm(-5)), ..] => (*src, *tmp, *count, *j),
+        _ => return None,
+    };
+    let x = match add(&code[1..])? {
+        Add { x, y, .. } if y == tmp => x,
+        _ => return None,
+    };
+
+    let distinct = x != count && tmp != count && j == count;
+    let fixed = !matches!(src, Reg(r) if [x, tmp, count].contains(&r));
+    (distinct && fixed).then_some(Mul { x, src, tmp, count })
+}
+
+pub fn machine(program: Vec<Inst>) -> Machine<Inst> {
+    Machine::new(program, REGISTERS.len())
+}
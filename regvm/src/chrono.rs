@@ -0,0 +1,125 @@
+//! 2024 day 17: a 3-bit computer with registers A, B and C. Programs
+//! are lists of opcode and operand words; jumps target words, so only
+//! even targets land on an instruction.
+
+use crate::{Flow, Machine, ParseError};
+
+pub const A: usize = 0;
+pub const B: usize = 1;
+pub const C: usize = 2;
+
+/// A combo operand: 0 to 3 are literal, 4 to 6 read A, B and C.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Combo(u64);
+
+impl Combo {
+    fn get(self, regs: &[u64]) -> u64 {
+        match self.0 {
+            n @ 0..=3 => n,
+            n => regs[n as usize - 4],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Adv(Combo),
+    Bxl(u64),
+    Bst(Combo),
+    Jnz(u64),
+    Bxc,
+    Out(Combo),
+    Bdv(Combo),
+    Cdv(Combo),
+}
+
+impl crate::Inst for Inst {
+    type Word = u64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        use Inst::*;
+
+        let r = m.regs_mut();
+        let shift = |a: u64, n: u64| u32::try_from(n).ok().and_then(|n| a.checked_shr(n)).unwrap_or(0);
+        match *self {
+            Adv(x) => r[A] = shift(r[A], x.get(r)),
+            Bxl(n) => r[B] ^= n,
+            Bst(x) => r[B] = x.get(r) % 8,
+            Jnz(n) if r[A] != 0 => return Flow::Goto(n as i64 / 2),
+            Jnz(_) => {},
+            Bxc => r[B] ^= r[C],
+            Out(x) => {
+                let v = x.get(r) % 8;
+                m.write_output(v)
+            },
+            Bdv(x) => r[B] = shift(r[A], x.get(r)),
+            Cdv(x) => r[C] = shift(r[A], x.get(r)),
+        }
+
+        Flow::Next
+    }
+}
+
+/// Parse the puzzle input into the initial registers and the program
+/// words.
+pub fn parse(input: &str) -> Result<([u64; 3], Vec<u64>), ParseError> {
+    let error = |line: usize| ParseError {
+        line: line + 1,
+        text: input.lines().nth(line).unwrap_or_default().to_string()
+    };
+
+    let mut regs = [0; 3];
+    let mut words = None;
+    for (i, line) in input.lines().enumerate() {
+        if let Some((name, v)) = line.strip_prefix("Register ").and_then(|s| s.split_once(": ")) {
+            let r = match name {
+                "A" => A,
+                "B" => B,
+                "C" => C,
+                _ => return Err(error(i)),
+            };
+            regs[r] = v.parse().map_err(|_| error(i))?;
+        } else if let Some(s) = line.strip_prefix("Program: ") {
+            let w = s.split(',')
+                .map(|n| n.parse::<u64>().ok().filter(|n| *n < 8))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| error(i))?;
+            words = Some(w);
+        } else if !line.trim().is_empty() {
+            return Err(error(i))
+        }
+    }
+
+    let words = words.ok_or_else(|| error(input.lines().count()))?;
+    Ok((regs, words))
+}
+
+/// Decode program words into instructions, one per pair.
+pub fn decode(words: &[u64]) -> Result<Vec<Inst>, ParseError> {
+    use Inst::*;
+
+    words.chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let combo = |n: u64| (n < 7).then_some(Combo(n));
+            let inst = match *pair {
+                [0, n] => combo(n).map(Adv),
+                [1, n] => Some(Bxl(n)),
+                [2, n] => combo(n).map(Bst),
+                [3, n] => (n % 2 == 0).then_some(Jnz(n)),
+                [4, _] => Some(Bxc),
+                [5, n] => combo(n).map(Out),
+                [6, n] => combo(n).map(Bdv),
+                [7, n] => combo(n).map(Cdv),
+                _ => None,
+            };
+            inst.ok_or_else(|| ParseError { line: 1, text: format!("{pair:?} at word {}", i * 2) })
+        })
+        .collect()
+}
+
+pub fn machine(regs: [u64; 3], program: Vec<Inst>) -> Machine<Inst> {
+    let mut m = Machine::new(program, 3);
+    m.regs_mut().copy_from_slice(&regs);
+    m
+}
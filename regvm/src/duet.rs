@@ -0,0 +1,82 @@
+//! 2017 days 18 and 23: registers `a` to `z`. `snd` writes to the
+//! output and `rcv` reads from the input, waiting when it's empty.
+
+use crate::{operand, parse_lines, register, Flow, Machine, Operand, ParseError};
+
+pub mod network;
+
+const REGISTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+type Value = Operand<i64>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Snd(Value),
+    Set(usize, Value),
+    Add(usize, Value),
+    Sub(usize, Value),
+    Mul(usize, Value),
+    Mod(usize, Value),
+    Rcv(usize),
+    Jgz(Value, Value),
+    Jnz(Value, Value),
+}
+
+impl crate::Inst for Inst {
+    type Word = i64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        use Inst::*;
+
+        let regs = m.regs_mut();
+        match *self {
+            Snd(x) => {
+                let v = x.get(regs);
+                m.write_output(v)
+            },
+            Set(r, y) => regs[r] = y.get(regs),
+            Add(r, y) => regs[r] += y.get(regs),
+            Sub(r, y) => regs[r] -= y.get(regs),
+            Mul(r, y) => regs[r] *= y.get(regs),
+            Mod(r, y) => regs[r] %= y.get(regs),
+            Rcv(r) => match m.read_input() {
+                Some(v) => m.set_reg(r, v),
+                None => return Flow::Wait,
+            },
+            Jgz(x, y) if x.get(regs) > 0 => return Flow::Jump(y.get(regs)),
+            Jnz(x, y) if x.get(regs) != 0 => return Flow::Jump(y.get(regs)),
+            Jgz(..) | Jnz(..) => {},
+        }
+
+        Flow::Next
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Inst>, ParseError> {
+    parse_lines(input, |tokens| {
+        let reg = |s| register(s, REGISTERS);
+        let val = |s| operand(s, REGISTERS);
+        let inst = match tokens {
+            ["snd", x] => Inst::Snd(val(x)?),
+            ["set", r, y] => Inst::Set(reg(r)?, val(y)?),
+            ["add", r, y] => Inst::Add(reg(r)?, val(y)?),
+            ["sub", r, y] => Inst::Sub(reg(r)?, val(y)?),
+            ["mul", r, y] => Inst::Mul(reg(r)?, val(y)?),
+            ["mod", r, y] => Inst::Mod(reg(r)?, val(y)?),
+            ["rcv", r] => Inst::Rcv(reg(r)?),
+            ["jgz", x, y] => Inst::Jgz(val(x)?, val(y)?),
+            ["jnz", x, y] => Inst::Jnz(val(x)?, val(y)?),
+            _ => return None,
+        };
+        Some(inst)
+    })
+}
+
+pub fn machine(program: Vec<Inst>) -> Machine<Inst> {
+    Machine::new(program, REGISTERS.len())
+}
+
+/// Register index of a name, `p` holds the program id.
+pub fn reg(name: char) -> usize {
+    REGISTERS.find(name).expect("registers are a to z")
+}
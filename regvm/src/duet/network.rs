@@ -0,0 +1,113 @@
+//! Day 18's second part: copies of a program running together, each
+//! sending its `snd` values to another's `rcv` queue.
+
+use super::{machine, reg, Inst};
+use crate::{Machine, State};
+
+/// Instructions a program runs before the next gets a turn.
+const SLICE: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Messages sent.
+    pub sent: usize,
+    /// Instructions executed.
+    pub steps: u64,
+}
+
+/// How a run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    /// Every program ran off the end of its code.
+    Halted,
+    /// Every program still running is waiting on an empty queue.
+    Deadlock,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub end: End,
+    /// By program id.
+    pub stats: Vec<Stats>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    machine: Machine<Inst>,
+    to: usize,
+    sent: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Network {
+    nodes: Vec<Node>,
+}
+
+impl Network {
+    /// `n` copies of a program with their ids in register `p`, each
+    /// sending to the next and the last to the first.
+    pub fn ring(program: &[Inst], n: usize) -> Self {
+        let nodes = (0..n)
+            .map(|id| {
+                let mut m = machine(program.to_vec());
+                m.set_reg(reg('p'), id as i64);
+                Node { machine: m, to: (id + 1) % n, sent: 0 }
+            })
+            .collect();
+
+        Network { nodes }
+    }
+
+    /// Send program `from`'s messages to program `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.nodes.len(), "no program {to}");
+        self.nodes[from].to = to
+    }
+
+    pub fn machine(&self, id: usize) -> &Machine<Inst> {
+        &self.nodes[id].machine
+    }
+
+    pub fn stats(&self) -> Vec<Stats> {
+        self.nodes.iter()
+            .map(|n| Stats { sent: n.sent, steps: n.machine.steps() })
+            .collect()
+    }
+
+    /// Run the programs in turn, passing on what each sends, until
+    /// none of them can go any further.
+    pub fn run(&mut self) -> Report {
+        loop {
+            for id in 0..self.nodes.len() {
+                let node = &mut self.nodes[id];
+                let limit = node.machine.steps() + SLICE;
+                node.machine.run_until(|m| m.steps() >= limit);
+
+                let sent = node.machine.drain();
+                node.sent += sent.len();
+                let to = node.to;
+                sent.into_iter().for_each(|v| self.nodes[to].machine.write(v));
+            }
+
+            if let Some(end) = self.end() {
+                return Report { end, stats: self.stats() }
+            }
+        }
+    }
+
+    fn end(&self) -> Option<End> {
+        let stuck = |m: &Machine<Inst>| match m.state() {
+            State::Halted => true,
+            State::Waiting => !m.has_input(),
+            State::Running => false,
+        };
+
+        if self.nodes.iter().all(|n| n.machine.is_halted()) {
+            Some(End::Halted)
+        } else if self.nodes.iter().all(|n| stuck(&n.machine)) {
+            Some(End::Deadlock)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! 2018 days 16, 19 and 21: sixteen three-operand opcodes over six
+//! registers, with the instruction pointer optionally bound to one of
+//! them by an `#ip` directive.
+
+use crate::{parse_lines, tokens, Flow, Machine, ParseError};
+
+pub mod analysis;
+
+pub const REGISTERS: usize = 6;
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    addr, addi,
+    mulr, muli,
+    banr, bani,
+    borr, bori,
+    setr, seti,
+    gtir, gtri, gtrr,
+    eqir, eqri, eqrr,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 16] = {
+        use Opcode::*;
+        [
+            addr, addi, mulr, muli, banr, bani, borr, bori,
+            setr, seti, gtir, gtri, gtrr, eqir, eqri, eqrr,
+        ]
+    };
+
+    pub fn name(self) -> &'static str {
+        use Opcode::*;
+
+        match self {
+            addr => "addr", addi => "addi",
+            mulr => "mulr", muli => "muli",
+            banr => "banr", bani => "bani",
+            borr => "borr", bori => "bori",
+            setr => "setr", seti => "seti",
+            gtir => "gtir", gtri => "gtri", gtrr => "gtrr",
+            eqir => "eqir", eqri => "eqri", eqrr => "eqrr",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        Opcode::ALL.iter().copied().find(|op| op.name() == s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Inst {
+    pub op: Opcode,
+    pub a: i64,
+    pub b: i64,
+    pub c: i64,
+}
+
+impl Inst {
+    /// Apply the instruction to a register file.
+    pub fn apply(&self, r: &mut [i64]) {
+        use Opcode::*;
+
+        let (a, b) = (self.a, self.b);
+        let ra = |r: &[i64]| r[a as usize];
+        let rb = |r: &[i64]| r[b as usize];
+        r[self.c as usize] = match self.op {
+            addr => ra(r) + rb(r),
+            addi => ra(r) + b,
+            mulr => ra(r) * rb(r),
+            muli => ra(r) * b,
+            banr => ra(r) & rb(r),
+            bani => ra(r) & b,
+            borr => ra(r) | rb(r),
+            bori => ra(r) | b,
+            setr => ra(r),
+            seti => a,
+            gtir => (a > rb(r)) as i64,
+            gtri => (ra(r) > b) as i64,
+            gtrr => (ra(r) > rb(r)) as i64,
+            eqir => (a == rb(r)) as i64,
+            eqri => (ra(r) == b) as i64,
+            eqrr => (ra(r) == rb(r)) as i64,
+        }
+    }
+}
+
+impl crate::Inst for Inst {
+    type Word = i64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        match m.bound_ip() {
+            Some(ipr) => {
+                let ip = m.ip() as i64;
+                m.set_reg(ipr, ip);
+                self.apply(m.regs_mut());
+                Flow::Goto(m.reg(ipr) + 1)
+            },
+            None => {
+                self.apply(m.regs_mut());
+                Flow::Next
+            },
+        }
+    }
+}
+
+/// A listing with an optional leading `#ip` directive.
+pub fn parse(input: &str) -> Result<(Option<usize>, Vec<Inst>), ParseError> {
+    let first = input.lines().next().unwrap_or_default();
+    let (ipr, body, offset) = match tokens(first)[..] {
+        ["#ip", r] => {
+            let r = r.parse::<usize>().ok()
+                .filter(|r| *r < REGISTERS)
+                .ok_or_else(|| ParseError { line: 1, text: first.to_string() })?;
+            (Some(r), input.split_once('\n').map_or("", |(_, s)| s), 1)
+        },
+        _ => (None, input, 0),
+    };
+
+    let program = parse_lines(body, |tokens| match tokens {
+        [op, a, b, c] => Some(Inst {
+            op: Opcode::from_name(op)?,
+            a: a.parse().ok()?,
+            b: b.parse().ok()?,
+            c: c.parse().ok().filter(|c| (0..REGISTERS as i64).contains(c))?,
+        }),
+        _ => None,
+    })
+    .map_err(|e| ParseError { line: e.line + offset, ..e })?;
+
+    Ok((ipr, program))
+}
+
+pub fn machine(ipr: Option<usize>, program: Vec<Inst>) -> Machine<Inst> {
+    let mut m = Machine::new(program, REGISTERS);
+    if let Some(r) = ipr {
+        m.bind_ip(r)
+    }
+    m
+}
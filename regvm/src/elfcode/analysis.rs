@@ -0,0 +1,260 @@
+//! Control flow and loop recognition for elfcode. Days 19 and 21 hide
+//! a slow algorithm in a short program; finding its loop, and the
+//! registers and constants it works with, lets the answer be computed
+//! directly.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use super::{Inst, Opcode, REGISTERS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    To(usize),
+    Halt,
+    /// The target depends on a register that isn't known statically.
+    Computed,
+}
+
+/// A run of instructions entered at the top and left at the bottom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub range: Range<usize>,
+    pub next: Vec<Edge>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+}
+
+impl Cfg {
+    /// Build the graph of a program with the instruction pointer
+    /// bound to register `ipr`.
+    pub fn new(ipr: usize, program: &[Inst]) -> Self {
+        let jumps = (0..program.len())
+            .map(|ip| jump(ipr, program, ip))
+            .collect::<Vec<_>>();
+
+        let mut leaders = BTreeSet::from([0]);
+        for (ip, edges) in jumps.iter().enumerate() {
+            if let Some(edges) = edges {
+                leaders.insert(ip + 1);
+                leaders.extend(edges.iter().filter_map(|e| match e {
+                    Edge::To(t) => Some(*t),
+                    _ => None,
+                }));
+            }
+        }
+        leaders.retain(|ip| *ip < program.len());
+
+        let starts = leaders.into_iter().collect::<Vec<_>>();
+        let blocks = starts.iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(program.len());
+                let next = jumps[end - 1].clone()
+                    .unwrap_or_else(|| vec![edge(program, end as i64)]);
+                Block { range: start..end, next }
+            })
+            .collect();
+
+        Cfg { blocks }
+    }
+
+    /// The block holding instruction `ip`.
+    pub fn block(&self, ip: usize) -> Option<&Block> {
+        self.blocks.iter().find(|b| b.range.contains(&ip))
+    }
+
+    /// Address ranges of the loops, from each back edge to the start
+    /// of the block it jumps to, shortest first.
+    pub fn loops(&self) -> Vec<Range<usize>> {
+        let mut loops = self.blocks.iter()
+            .flat_map(|b| b.next.iter().filter_map(move |e| match e {
+                Edge::To(t) if *t <= b.range.start => Some(*t..b.range.end),
+                _ => None,
+            }))
+            .collect::<Vec<_>>();
+
+        loops.sort_by_key(|l| l.len());
+        loops
+    }
+}
+
+/// Registers an instruction reads.
+pub fn reads(inst: &Inst) -> Vec<usize> {
+    use Opcode::*;
+
+    let (a, b) = (inst.a as usize, inst.b as usize);
+    match inst.op {
+        addr | mulr | banr | borr | gtrr | eqrr => vec![a, b],
+        addi | muli | bani | bori | gtri | eqri | setr => vec![a],
+        gtir | eqir => vec![b],
+        seti => vec![],
+    }
+}
+
+fn is_compare(op: Opcode) -> bool {
+    use Opcode::*;
+
+    matches!(op, gtir | gtri | gtrr | eqir | eqri | eqrr)
+}
+
+fn edge(program: &[Inst], target: i64) -> Edge {
+    match usize::try_from(target) {
+        Ok(t) if t < program.len() => Edge::To(t),
+        _ => Edge::Halt,
+    }
+}
+
+/// Where control can go after the instruction at `ip`, or `None` if it
+/// doesn't write the instruction pointer.
+fn jump(ipr: usize, program: &[Inst], ip: usize) -> Option<Vec<Edge>> {
+    let inst = &program[ip];
+    if inst.c as usize != ipr {
+        return None
+    }
+
+    let regs = reads(inst);
+    if regs.iter().all(|r| *r == ipr) {
+        let mut r = [0; REGISTERS];
+        r[ipr] = ip as i64;
+        inst.apply(&mut r);
+        return Some(vec![edge(program, r[ipr] + 1)])
+    }
+
+    // Adding a comparison result skips the next instruction or not.
+    let flag = match regs[..] {
+        [a, b] if inst.op == Opcode::addr && a == ipr => b,
+        [a, b] if inst.op == Opcode::addr && b == ipr => a,
+        _ => return Some(vec![Edge::Computed]),
+    };
+    let compared = ip.checked_sub(1)
+        .map(|i| &program[i])
+        .is_some_and(|prev| prev.c as usize == flag && is_compare(prev.op));
+
+    if compared {
+        Some(vec![edge(program, ip as i64 + 1), edge(program, ip as i64 + 2)])
+    } else {
+        Some(vec![Edge::Computed])
+    }
+}
+
+/// The slow loop at the heart of a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Adds every divisor of register `n` to register 0, testing all
+    /// pairs of numbers up to it. `n` is set by the time instruction
+    /// `at` runs.
+    SumOfDivisors { at: usize, n: usize },
+    /// Halts when register 0 equals register `reg` at instruction
+    /// `at`, where `reg` steps through `hash` from one check to the
+    /// next.
+    Hash { at: usize, reg: usize, hash: Hash },
+}
+
+/// The day 21 hash, which feeds the bytes of the previous value
+/// through a multiply and mask, finding each byte by counting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hash {
+    pub seed: i64,
+    pub or: i64,
+    pub mult: i64,
+    pub mask: i64,
+    pub base: i64,
+}
+
+impl Hash {
+    pub fn next(&self, h: i64) -> i64 {
+        let mut x = h | self.or;
+        let mut h = self.seed;
+        loop {
+            h = (((h + (x & (self.base - 1))) & self.mask) * self.mult) & self.mask;
+            if x < self.base {
+                return h
+            }
+            x /= self.base;
+        }
+    }
+}
+
+/// Recognize the kernel of a program with the instruction pointer
+/// bound to register `ipr`.
+pub fn kernel(ipr: usize, program: &[Inst]) -> Option<Kernel> {
+    let cfg = Cfg::new(ipr, program);
+    sum_of_divisors_loop(program, &cfg).or_else(|| hash_loop(program, &cfg))
+}
+
+pub fn sum_of_divisors(n: i64) -> i64 {
+    (1..)
+        .take_while(|i| i * i <= n)
+        .filter(|i| n % i == 0)
+        .map(|i| if i * i == n { i } else { i + n / i })
+        .sum()
+}
+
+/// `t = i * j`, `t = t == n`, a skip and `r0 += i` inside a loop over
+/// `j`, itself inside a loop over `i`.
+fn sum_of_divisors_loop(program: &[Inst], cfg: &Cfg) -> Option<Kernel> {
+    use Opcode::*;
+
+    let loops = cfg.loops();
+    program.windows(2)
+        .enumerate()
+        .find_map(|(i, pair)| {
+            let (mul, eq) = (pair[0], pair[1]);
+            if mul.op != mulr || eq.op != eqrr {
+                return None
+            }
+            let n = match (eq.a, eq.b) {
+                (t, n) | (n, t) if t == mul.c && n != mul.c => n as usize,
+                _ => return None,
+            };
+
+            let inner = loops.iter().find(|l| l.contains(&i))?;
+            let nested = loops.iter()
+                .any(|l| l != inner && l.start <= inner.start && inner.end <= l.end);
+            let factor = |r: i64| r == mul.a || r == mul.b;
+            let adds = program[inner.clone()].iter()
+                .any(|inst| inst.op == addr && inst.c == 0 && (
+                    (inst.a == 0 && factor(inst.b)) || (inst.b == 0 && factor(inst.a))
+                ));
+
+            (nested && adds).then_some(Kernel::SumOfDivisors { at: i, n })
+        })
+}
+
+/// A comparison of register 0 with the hash that can halt, and the
+/// hash loop: `x = h | or`, `h = seed`, then `h += x & (base - 1)`,
+/// masked and multiplied, with `x /= base` until it's below `base`.
+fn hash_loop(program: &[Inst], cfg: &Cfg) -> Option<Kernel> {
+    use Opcode::*;
+
+    let (at, h) = program.iter()
+        .enumerate()
+        .find_map(|(i, inst)| {
+            let h = match (inst.op, inst.a, inst.b) {
+                (eqrr, 0, h) | (eqrr, h, 0) if h != 0 => h,
+                _ => return None,
+            };
+            cfg.block(i)?.next.contains(&Edge::Halt).then_some((i, h))
+        })?;
+
+    let start = program.iter().position(|inst| inst.op == bori && inst.a == h && inst.c != h)?;
+    let (or, x) = (program[start].b, program[start].c);
+    let seed = match program.get(start + 1) {
+        Some(Inst { op: seti, a, c, .. }) if *c == h => *a,
+        _ => return None,
+    };
+
+    let body = &program[start..];
+    let find = |f: &dyn Fn(&Inst) -> bool| body.iter().find(|inst| f(inst)).copied();
+    let mult = find(&|inst| inst.op == muli && inst.a == h && inst.c == h)?.b;
+    let mask = find(&|inst| inst.op == bani && inst.a == h && inst.c == h)?.b;
+    let base = find(&|inst| inst.op == bani && inst.a == x)?.b + 1;
+    find(&|inst| inst.op == gtir && inst.a == base && inst.b == x)?;
+
+    let hash = Hash { seed, or, mult, mask, base };
+    Some(Kernel::Hash { at, reg: h as usize, hash })
+}
@@ -0,0 +1,42 @@
+//! 2020 day 8: a single accumulator.
+
+use crate::{parse_lines, Flow, Machine, ParseError};
+
+pub const ACC: usize = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Acc(i64),
+    Jmp(i64),
+    Nop(i64),
+}
+
+impl crate::Inst for Inst {
+    type Word = i64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        match *self {
+            Inst::Acc(n) => m.regs_mut()[ACC] += n,
+            Inst::Jmp(n) => return Flow::Jump(n),
+            Inst::Nop(_) => {},
+        }
+
+        Flow::Next
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Inst>, ParseError> {
+    parse_lines(input, |tokens| {
+        let inst = match tokens {
+            ["acc", n] => Inst::Acc(n.parse().ok()?),
+            ["jmp", n] => Inst::Jmp(n.parse().ok()?),
+            ["nop", n] => Inst::Nop(n.parse().ok()?),
+            _ => return None,
+        };
+        Some(inst)
+    })
+}
+
+pub fn machine(program: Vec<Inst>) -> Machine<Inst> {
+    Machine::new(program, 1)
+}
@@ -0,0 +1,307 @@
+//! Register machines for the assembly style puzzles. Each dialect has
+//! its own instruction set and parser; they all run on `Machine`,
+//! which owns the program, the register file, the instruction pointer
+//! and the input and output queues.
+
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+pub mod alu;
+pub mod assembunny;
+pub mod chrono;
+pub mod duet;
+pub mod elfcode;
+pub mod handheld;
+pub mod turing;
+
+#[cfg(test)]
+mod test;
+
+/// An instruction of some dialect.
+pub trait Inst: Clone {
+    /// Register contents.
+    type Word: Copy + Default;
+
+    /// Execute the instruction, returning where to go next.
+    fn exec(&self, m: &mut Machine<Self>) -> Flow;
+}
+
+/// Where the instruction pointer goes after an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// Relative to the current instruction.
+    Jump(i64),
+    /// Absolute instruction address.
+    Goto(i64),
+    /// Blocked on an empty input; the instruction is retried.
+    Wait,
+    Halt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Running,
+    Waiting,
+    Halted,
+}
+
+/// Why a run stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Halted,
+    Waiting,
+    /// The `run_until` predicate held.
+    Hook,
+    /// About to execute this instruction a second time.
+    Repeat(usize),
+}
+
+/// A register operand or an immediate value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operand<W> {
+    Reg(usize),
+    Imm(W),
+}
+
+impl<W: Copy> Operand<W> {
+    pub fn get(&self, regs: &[W]) -> W {
+        match self {
+            Operand::Reg(r) => regs[*r],
+            Operand::Imm(n) => *n,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line number, starting at 1.
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid instruction {:?} on line {}", self.text, self.line)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Clone, Debug)]
+pub struct Machine<I: Inst> {
+    program: Vec<I>,
+    regs: Vec<I::Word>,
+    ip: usize,
+    bound: Option<usize>,
+    input: VecDeque<I::Word>,
+    output: Vec<I::Word>,
+    steps: u64,
+    state: State,
+}
+
+impl<I: Inst> Machine<I> {
+    pub fn new(program: Vec<I>, registers: usize) -> Self {
+        Machine {
+            program,
+            regs: vec![I::Word::default(); registers],
+            ip: 0,
+            bound: None,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            steps: 0,
+            state: State::Running,
+        }
+    }
+
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    /// The program is data to self-modifying dialects.
+    pub fn program_mut(&mut self) -> &mut [I] {
+        &mut self.program
+    }
+
+    pub fn reg(&self, r: usize) -> I::Word {
+        self.regs[r]
+    }
+
+    pub fn set_reg(&mut self, r: usize, v: I::Word) {
+        self.regs[r] = v
+    }
+
+    pub fn regs(&self) -> &[I::Word] {
+        &self.regs
+    }
+
+    pub fn regs_mut(&mut self) -> &mut [I::Word] {
+        &mut self.regs
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Jump to `ip`, halting if that's outside the program.
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+        self.state = if ip < self.program.len() { State::Running } else { State::Halted };
+    }
+
+    /// Mirror the instruction pointer in register `r`, as elfcode's
+    /// `#ip` directive does.
+    pub fn bind_ip(&mut self, r: usize) {
+        self.bound = Some(r)
+    }
+
+    pub fn bound_ip(&self) -> Option<usize> {
+        self.bound
+    }
+
+    pub fn write(&mut self, v: I::Word) {
+        self.input.push_back(v)
+    }
+
+    pub fn has_input(&self) -> bool {
+        !self.input.is_empty()
+    }
+
+    /// Next input value, for instructions that read.
+    pub fn read_input(&mut self) -> Option<I::Word> {
+        self.input.pop_front()
+    }
+
+    /// Output value, for instructions that write.
+    pub fn write_output(&mut self, v: I::Word) {
+        self.output.push(v)
+    }
+
+    pub fn output(&self) -> &[I::Word] {
+        &self.output
+    }
+
+    pub fn drain(&mut self) -> Vec<I::Word> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Number of instructions executed.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state == State::Halted
+    }
+
+    /// Execute a single instruction. The machine halts when the
+    /// instruction pointer leaves the program.
+    pub fn step(&mut self) -> State {
+        if self.state == State::Halted {
+            return self.state
+        }
+        let inst = match self.program.get(self.ip) {
+            Some(inst) => inst.clone(),
+            None => {
+                self.state = State::Halted;
+                return self.state
+            },
+        };
+
+        self.state = State::Running;
+        let next = match inst.exec(self) {
+            Flow::Next    => Some(self.ip as i64 + 1),
+            Flow::Jump(n) => Some(self.ip as i64 + n),
+            Flow::Goto(n) => Some(n),
+            Flow::Wait => {
+                self.state = State::Waiting;
+                return self.state
+            },
+            Flow::Halt => None,
+        };
+        self.steps += 1;
+
+        match next.and_then(|ip| usize::try_from(ip).ok()) {
+            Some(ip) if ip < self.program.len() => self.ip = ip,
+            _ => self.state = State::Halted,
+        }
+
+        self.state
+    }
+
+    /// Run until the machine halts or waits on input.
+    pub fn run(&mut self) -> State {
+        while self.step() == State::Running {}
+        self.state
+    }
+
+    /// Run until `hook` holds before an instruction is executed, the
+    /// machine halts or it waits on input.
+    pub fn run_until<F>(&mut self, mut hook: F) -> Stop
+        where F: FnMut(&Self) -> bool
+    {
+        loop {
+            if self.state == State::Halted {
+                return Stop::Halted
+            }
+            if hook(self) {
+                return Stop::Hook
+            }
+            if self.step() == State::Waiting {
+                return Stop::Waiting
+            }
+        }
+    }
+
+    /// Run until an instruction is about to execute a second time, a
+    /// loop for programs without conditional jumps.
+    pub fn run_until_repeat(&mut self) -> Stop {
+        let mut seen = HashSet::new();
+        let stop = self.run_until(|m| !seen.insert(m.ip));
+        match stop {
+            Stop::Hook => Stop::Repeat(self.ip),
+            stop => stop,
+        }
+    }
+}
+
+/// Tokens of an instruction, split on whitespace and commas.
+pub(crate) fn tokens(line: &str) -> Vec<&str> {
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Index of a single letter register name in `names`.
+pub(crate) fn register(s: &str, names: &str) -> Option<usize> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => names.find(c),
+        _ => None,
+    }
+}
+
+pub(crate) fn operand<W: std::str::FromStr>(s: &str, names: &str) -> Option<Operand<W>> {
+    match register(s, names) {
+        Some(r) => Some(Operand::Reg(r)),
+        None => s.parse().ok().map(Operand::Imm),
+    }
+}
+
+/// Parse a listing a line at a time, skipping blank lines.
+pub(crate) fn parse_lines<I, F>(input: &str, f: F) -> Result<Vec<I>, ParseError>
+    where F: Fn(&[&str]) -> Option<I>
+{
+    input.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| f(&tokens(line))
+            .ok_or_else(|| ParseError { line: i + 1, text: line.to_string() })
+        )
+        .collect()
+}
@@ -0,0 +1,282 @@
+use super::*;
+
+#[test]
+fn turing() {
+    let program = turing::parse("inc a\njio a, +2\ntpl a\ninc a").unwrap();
+    let mut m = turing::machine(program);
+
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.regs(), [2, 0]);
+    assert_eq!(m.steps(), 3);
+}
+
+#[test]
+fn assembunny() {
+    let program = assembunny::parse("cpy 41 a\ninc a\ninc a\ndec a\njnz a 2\ndec a").unwrap();
+    let mut m = assembunny::machine(program);
+    m.run();
+    assert_eq!(m.reg(0), 42);
+
+    let input = "cpy 2 a\ntgl a\ntgl a\ntgl a\ncpy 1 a\ndec a\ndec a";
+    let mut m = assembunny::machine(assembunny::parse(input).unwrap());
+    m.run();
+    assert_eq!(m.reg(0), 3);
+}
+
+#[test]
+fn assembunny_optimize() {
+    use assembunny::Inst;
+
+    let run = |program: Vec<Inst>| {
+        let mut m = assembunny::machine(program);
+        m.run();
+        (m.regs().to_vec(), m.steps())
+    };
+
+    let programs = [
+        // Addition, both ways round.
+        "cpy 4 b\ncpy 3 a\ninc a\ndec b\njnz b -2",
+        "cpy 4 b\ncpy 3 a\ndec b\ninc a\njnz b -2",
+        // Multiplication by a register and by a constant.
+        "cpy 6 b\ncpy 7 d\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5",
+        "cpy 7 d\ncpy 6 c\ndec c\ninc a\njnz c -2\ndec d\njnz d -5\ncpy 6 c",
+        // Counters that don't start positive run the loop as written.
+        "cpy 3 c\ncpy -1 b\ninc b\ndec c\njnz c -2",
+        // tgl breaks the loop: the jnz becomes a skipped cpy.
+        "cpy 4 b\ncpy 3 c\ntgl c\ninc a\ndec b\njnz b -2",
+        // tgl makes the loop: the inc b becomes a dec b.
+        "cpy 5 b\ncpy 2 c\ntgl c\ninc a\ninc b\njnz b -2",
+        // Day 23's example.
+        "cpy 2 a\ntgl a\ntgl a\ntgl a\ncpy 1 a\ndec a\ndec a",
+    ];
+
+    for input in programs {
+        let program = assembunny::parse(input).unwrap();
+        let (naive, slow) = run(program.clone());
+        let (optimized, fast) = run(assembunny::optimize(&program));
+
+        assert_eq!(naive, optimized, "{input}");
+        assert!(fast <= slow, "{input}");
+    }
+
+    let program = assembunny::parse(programs[2]).unwrap();
+    let optimized = assembunny::optimize(&program);
+    assert!(matches!(optimized[2], Inst::Mul { x: 0, tmp: 2, count: 3, .. }));
+    assert!(matches!(optimized[3], Inst::Add { x: 0, y: 2, inc_first: true }));
+    assert_eq!(run(optimized).1, 3);
+
+    let program = assembunny::parse(programs[6]).unwrap();
+    assert_eq!(run(assembunny::optimize(&program)).1, 4);
+
+    // A day 23 style factorial, where tgl turns the tail into a
+    // multiplication loop part way through.
+    let input = "cpy a b\ndec b\ncpy a d\ncpy 0 a\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\n\
+                 dec b\ncpy b c\ncpy c d\ndec d\ninc c\njnz d -2\ntgl c\ncpy -16 c\njnz 1 c\n\
+                 cpy 84 c\njnz 71 d\ninc a\ninc d\njnz d -2\ninc c\njnz c -5";
+    let program = assembunny::parse(input).unwrap();
+    let factorial = |program: Vec<Inst>, n| {
+        let mut m = assembunny::machine(program);
+        m.set_reg(0, n);
+        m.run();
+        m.reg(0)
+    };
+
+    assert_eq!(factorial(program.clone(), 7), 5040 + 84 * 71);
+    assert_eq!(factorial(assembunny::optimize(&program), 7), 5040 + 84 * 71);
+    assert_eq!(factorial(assembunny::optimize(&program), 12), 479001600 + 84 * 71);
+}
+
+#[test]
+fn duet() {
+    let program = duet::parse("snd 1\nsnd 2\nsnd p\nrcv a\nrcv b\nrcv c\nrcv d").unwrap();
+    let mut m = duet::machine(program);
+    m.set_reg(duet::reg('p'), 1);
+
+    assert_eq!(m.run(), State::Waiting);
+    assert_eq!(m.drain(), [1, 2, 1]);
+    [1, 2, 0].iter().for_each(|v| m.write(*v));
+    assert_eq!(m.run(), State::Waiting);
+    assert_eq!(m.ip(), 6);
+
+    m.write(9);
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.reg(duet::reg('d')), 9);
+}
+
+#[test]
+fn duet_network() {
+    use duet::network::{End, Network, Stats};
+
+    let program = duet::parse("snd 1\nsnd 2\nsnd p\nrcv a\nrcv b\nrcv c\nrcv d").unwrap();
+    let report = Network::ring(&program, 2).run();
+    assert_eq!(report.end, End::Deadlock);
+    assert_eq!(report.stats, [Stats { sent: 3, steps: 6 }, Stats { sent: 3, steps: 6 }]);
+
+    // Each passes its id on and waits for a second message.
+    let program = duet::parse("snd p\nrcv a\nrcv b").unwrap();
+    let mut network = Network::ring(&program, 3);
+    assert_eq!(network.run().end, End::Deadlock);
+    let got = (0..3).map(|id| network.machine(id).reg(duet::reg('a'))).collect::<Vec<_>>();
+    assert_eq!(got, [2, 0, 1]);
+
+    let program = duet::parse("snd p\nrcv a").unwrap();
+    let report = Network::ring(&program, 4).run();
+    assert_eq!(report.end, End::Halted);
+    assert!(report.stats.iter().all(|s| s.sent == 1 && s.steps == 2));
+
+    // 0 and 1 both send to 2, which sends to 0, so 1 gets nothing.
+    let mut network = Network::ring(&program, 3);
+    network.connect(2, 0);
+    network.connect(0, 2);
+    assert_eq!(network.run().end, End::Deadlock);
+    assert_eq!(network.machine(1).state(), State::Waiting);
+}
+
+#[test]
+fn elfcode() {
+    use elfcode::{Inst, Opcode};
+
+    let input = "#ip 0\nseti 5 0 1\nseti 6 0 2\naddi 0 1 0\naddr 1 2 3\nsetr 1 0 0\nseti 8 0 4\nseti 9 0 5";
+    let (ipr, program) = elfcode::parse(input).unwrap();
+    let mut m = elfcode::machine(ipr, program);
+
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.regs(), [6, 5, 6, 0, 0, 9]);
+
+    // Before: [3, 2, 1, 1], 9 2 1 2, After: [3, 2, 2, 1]
+    let matches = Opcode::ALL.iter()
+        .filter(|&&op| {
+            let mut r = [3, 2, 1, 1];
+            Inst { op, a: 2, b: 1, c: 2 }.apply(&mut r);
+            r == [3, 2, 2, 1]
+        })
+        .count();
+    assert_eq!(matches, 3);
+
+    let err = elfcode::parse("#ip 0\nseti 5 0 1\nboor 1 2 3").unwrap_err();
+    assert_eq!(err, ParseError { line: 3, text: "boor 1 2 3".to_string() });
+}
+
+#[test]
+fn elfcode_analysis() {
+    use elfcode::analysis::{self, Cfg, Edge, Kernel};
+
+    // A day 19 style program: sum the divisors of a number built by
+    // the code at the end, a bigger one when register 0 starts at 1.
+    let input = "#ip 3\naddi 3 16 3\nseti 1 8 1\nseti 1 3 4\nmulr 1 4 2\neqrr 2 5 2\naddr 2 3 3\n\
+                 addi 3 1 3\naddr 1 0 0\naddi 4 1 4\ngtrr 4 5 2\naddr 3 2 3\nseti 2 3 3\naddi 1 1 1\n\
+                 gtrr 1 5 2\naddr 2 3 3\nseti 1 6 3\nmulr 3 3 3\naddi 5 2 5\nmulr 5 5 5\nmulr 3 5 5\n\
+                 muli 5 11 5\naddi 2 1 2\nmulr 2 3 2\naddi 2 7 2\naddr 5 2 5\naddr 3 0 3\nseti 0 1 3\n\
+                 setr 3 9 2\nmulr 2 3 2\naddr 3 2 2\nmulr 3 2 2\nmuli 2 14 2\nmulr 2 3 2\naddr 5 2 5\n\
+                 seti 0 1 0\nseti 0 0 3";
+    let (ipr, program) = elfcode::parse(input).unwrap();
+    let ipr = ipr.unwrap();
+
+    let cfg = Cfg::new(ipr, &program);
+    assert_eq!(cfg.block(0).unwrap().next, [Edge::To(17)]);
+    assert_eq!(cfg.block(4).unwrap().next, [Edge::To(6), Edge::To(7)]);
+    assert_eq!(cfg.block(16).unwrap().next, [Edge::Halt]);
+    assert_eq!(cfg.block(25).unwrap().next, [Edge::Computed]);
+    assert_eq!(cfg.loops(), [3..12, 2..16, 1..27, 1..36]);
+
+    let (at, n) = match analysis::kernel(ipr, &program) {
+        Some(Kernel::SumOfDivisors { at, n }) => (at, n),
+        kernel => panic!("{kernel:?}"),
+    };
+    assert_eq!((at, n), (3, 5));
+
+    let mut m = elfcode::machine(Some(ipr), program.clone());
+    m.run_until(|m| m.ip() == at);
+    assert_eq!(m.reg(n), 865);
+    m.run();
+    assert_eq!(m.reg(0), analysis::sum_of_divisors(865));
+
+    let mut m = elfcode::machine(Some(ipr), program);
+    m.set_reg(0, 1);
+    m.run_until(|m| m.ip() == at);
+    assert_eq!(m.reg(n), 10551265);
+
+    // A day 21 style program: halt when register 0 matches the hash.
+    let input = "#ip 4\nseti 123 0 3\nbani 3 456 3\neqri 3 72 3\naddr 3 4 4\nseti 0 0 4\nseti 0 4 3\n\
+                 bori 3 65536 2\nseti 1397714 1 3\nbani 2 255 5\naddr 3 5 3\nbani 3 16777215 3\n\
+                 muli 3 65899 3\nbani 3 16777215 3\ngtir 256 2 5\naddr 5 4 4\naddi 4 1 4\nseti 27 9 4\n\
+                 seti 0 9 5\naddi 5 1 1\nmuli 1 256 1\ngtrr 1 2 1\naddr 1 4 4\naddi 4 1 4\nseti 25 4 4\n\
+                 addi 5 1 5\nseti 17 1 4\nsetr 5 6 2\nseti 7 8 4\neqrr 3 0 5\naddr 5 4 4\nseti 5 5 4";
+    let (ipr, program) = elfcode::parse(input).unwrap();
+    let ipr = ipr.unwrap();
+
+    let (at, reg, hash) = match analysis::kernel(ipr, &program) {
+        Some(Kernel::Hash { at, reg, hash }) => (at, reg, hash),
+        kernel => panic!("{kernel:?}"),
+    };
+    assert_eq!((at, reg, hash.seed, hash.mult, hash.base), (28, 3, 1397714, 65899, 256));
+
+    let mut m = elfcode::machine(Some(ipr), program);
+    let mut h = 0;
+    for _ in 0..3 {
+        m.run_until(|m| m.ip() == at);
+        h = hash.next(h);
+        assert_eq!(m.reg(reg), h);
+        m.step();
+    }
+}
+
+#[test]
+fn handheld() {
+    let input = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+    let mut m = handheld::machine(handheld::parse(input).unwrap());
+
+    assert_eq!(m.run_until_repeat(), Stop::Repeat(1));
+    assert_eq!(m.reg(handheld::ACC), 5);
+}
+
+#[test]
+fn alu() {
+    let program = alu::parse("inp z\ninp x\nmul z 3\neql z x").unwrap();
+    let mut m = alu::machine(program);
+
+    m.write(3);
+    assert_eq!(m.run(), State::Waiting);
+    m.write(9);
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.reg(alu::reg('z')), 1);
+}
+
+#[test]
+fn chrono() {
+    let input = "Register A: 729\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0";
+    let (regs, words) = chrono::parse(input).unwrap();
+    let mut m = chrono::machine(regs, chrono::decode(&words).unwrap());
+
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.output(), [4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+    assert!(chrono::decode(&[3, 1]).is_err());
+}
+
+#[test]
+fn run_until() {
+    let program = turing::parse("inc a\njmp -1").unwrap();
+    let mut m = turing::machine(program);
+
+    assert_eq!(m.run_until(|m| m.reg(0) == 10), Stop::Hook);
+    assert_eq!(m.steps(), 19);
+    assert_eq!(m.ip(), 1);
+    m.step();
+    m.step();
+    assert_eq!(m.reg(0), 11);
+}
+
+#[test]
+fn set_ip() {
+    let program = turing::parse("inc a\ninc a").unwrap();
+    let mut m = turing::machine(program);
+
+    m.set_ip(1);
+    assert_eq!(m.run(), State::Halted);
+    assert_eq!(m.reg(0), 1);
+
+    // Jumping off the end halts before the hook can look there
+    m.set_ip(2);
+    assert_eq!(m.state(), State::Halted);
+    assert_eq!(m.run_until(|m| m.program()[m.ip()] == m.program()[0]), Stop::Halted);
+}
@@ -0,0 +1,59 @@
+//! 2015 day 23: two unsigned registers, `a` and `b`.
+
+use crate::{parse_lines, register, Flow, Machine, ParseError};
+
+const REGISTERS: &str = "ab";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    Hlf(usize),
+    Tpl(usize),
+    Inc(usize),
+    Jmp(i64),
+    /// Jump if even.
+    Jie(usize, i64),
+    /// Jump if one.
+    Jio(usize, i64),
+}
+
+impl crate::Inst for Inst {
+    type Word = u64;
+
+    fn exec(&self, m: &mut Machine<Self>) -> Flow {
+        use Inst::*;
+
+        let regs = m.regs_mut();
+        match *self {
+            Hlf(r) => regs[r] /= 2,
+            Tpl(r) => regs[r] *= 3,
+            Inc(r) => regs[r] += 1,
+            Jmp(n) => return Flow::Jump(n),
+            Jie(r, n) if regs[r].is_multiple_of(2) => return Flow::Jump(n),
+            Jio(r, n) if regs[r] == 1 => return Flow::Jump(n),
+            Jie(..) | Jio(..) => {},
+        }
+
+        Flow::Next
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Inst>, ParseError> {
+    parse_lines(input, |tokens| {
+        let reg = |s| register(s, REGISTERS);
+        let inst = match tokens {
+            ["hlf", r] => Inst::Hlf(reg(r)?),
+            ["tpl", r] => Inst::Tpl(reg(r)?),
+            ["inc", r] => Inst::Inc(reg(r)?),
+            ["jmp", n] => Inst::Jmp(n.parse().ok()?),
+            ["jie", r, n] => Inst::Jie(reg(r)?, n.parse().ok()?),
+            ["jio", r, n] => Inst::Jio(reg(r)?, n.parse().ok()?),
+            _ => return None,
+        };
+        Some(inst)
+    })
+}
+
+pub fn machine(program: Vec<Inst>) -> Machine<Inst> {
+    Machine::new(program, REGISTERS.len())
+}
+